@@ -0,0 +1,224 @@
+//
+// Token-2022 confidential transfer helpers.
+//
+// Encrypted amounts move through the ConfidentialTransfer extension's
+// `TransferWithSplitProofs` instruction. The equality, ciphertext validity and
+// range proofs are verified beforehand by the ZK Token Proof program into context
+// state accounts, which the transfer references.
+//
+// Amounts are split into low and high bits by Token-2022. The helpers below keep
+// both halves so that encrypted balances can be updated homomorphically.
+//
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_spl::token_2022::spl_token_2022::{
+    extension::{
+        confidential_transfer::{
+            ciphertext_extraction::SourceDecryptHandles,
+            instruction::{
+                inner_apply_pending_balance, transfer_with_split_proofs,
+                ConfidentialTransferInstruction, TransferSplitContextStateAccounts,
+            },
+            verify_proof::verify_transfer_proof,
+        },
+        ExtensionType,
+    },
+    instruction::{reallocate, TokenInstruction},
+    solana_zk_token_sdk::zk_token_elgamal::{
+        ops,
+        pod::{AeCiphertext, DecryptHandle, ElGamalCiphertext, TransferAmountCiphertext},
+    },
+};
+
+use crate::{ConfidentialTransferArgs, ErrorCode};
+
+/// An ElGamal ciphertext of zero, used to initialize encrypted balances.
+pub const ZERO_CIPHERTEXT: [u8; 64] = [0u8; 64];
+
+/// Accounts taking part in a confidential transfer.
+pub struct ConfidentialTransferAccounts<'info> {
+    pub token_program: AccountInfo<'info>,
+    pub source: AccountInfo<'info>,
+    pub mint: AccountInfo<'info>,
+    pub destination: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+    pub equality_proof: AccountInfo<'info>,
+    pub ciphertext_validity_proof: AccountInfo<'info>,
+    pub range_proof: AccountInfo<'info>,
+}
+
+/// Transfer amount encrypted under the destination ElGamal key,
+/// as a (low bits, high bits) ciphertext pair.
+pub struct EncryptedAmount {
+    pub destination: (ElGamalCiphertext, ElGamalCiphertext),
+}
+
+/// Moves an encrypted amount from `source` to `destination` with Token-2022.
+/// Returns the transfer amount ciphertexts extracted from the verified proofs.
+pub fn transfer(
+    accounts: ConfidentialTransferAccounts<'_>,
+    args: &ConfidentialTransferArgs,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<EncryptedAmount> {
+    let source_decrypt_handles = SourceDecryptHandles {
+        lo: DecryptHandle(args.source_decrypt_handle_lo),
+        hi: DecryptHandle(args.source_decrypt_handle_hi),
+    };
+
+    let proof_accounts = [
+        accounts.equality_proof.clone(),
+        accounts.ciphertext_validity_proof.clone(),
+        accounts.range_proof.clone(),
+    ];
+    let proof_context = verify_transfer_proof(
+        &mut proof_accounts.iter(),
+        0,
+        true,
+        false,
+        false,
+        &source_decrypt_handles,
+    )?
+    .ok_or(ErrorCode::InvalidConfidentialProof)?;
+
+    let ix = transfer_with_split_proofs(
+        accounts.token_program.key,
+        accounts.source.key,
+        accounts.mint.key,
+        accounts.destination.key,
+        AeCiphertext(args.new_source_decryptable_available_balance),
+        accounts.authority.key,
+        TransferSplitContextStateAccounts {
+            equality_proof: accounts.equality_proof.key,
+            ciphertext_validity_proof: accounts.ciphertext_validity_proof.key,
+            range_proof: accounts.range_proof.key,
+            authority: accounts.authority.key,
+            no_op_on_uninitialized_split_context_state: false,
+            close_split_context_state_accounts: None,
+        },
+        &source_decrypt_handles,
+    )?;
+    invoke_signed(
+        &ix,
+        &[
+            accounts.source,
+            accounts.mint,
+            accounts.destination,
+            accounts.equality_proof,
+            accounts.ciphertext_validity_proof,
+            accounts.range_proof,
+            accounts.authority,
+        ],
+        signer_seeds,
+    )?;
+
+    Ok(EncryptedAmount {
+        destination: (
+            transfer_amount_destination_ciphertext(&proof_context.ciphertext_lo),
+            transfer_amount_destination_ciphertext(&proof_context.ciphertext_hi),
+        ),
+    })
+}
+
+/// Adds an encrypted (low, high) amount to an encrypted balance.
+pub fn add(
+    balance: &[u8; 64],
+    amount: &(ElGamalCiphertext, ElGamalCiphertext),
+) -> Result<[u8; 64]> {
+    ops::add_with_lo_hi(&ElGamalCiphertext(*balance), &amount.0, &amount.1)
+        .map(|ciphertext| ciphertext.0)
+        .ok_or_else(|| ErrorCode::InvalidConfidentialProof.into())
+}
+
+/// Enables confidential transfers on a program-owned token account.
+/// The account is first reallocated to hold the ConfidentialTransferAccount extension.
+#[allow(clippy::too_many_arguments)]
+pub fn configure_account<'info>(
+    token_program: AccountInfo<'info>,
+    token_account: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    pubkey_validity_proof: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    decryptable_zero_balance: [u8; 36],
+    maximum_pending_balance_credit_counter: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let realloc_ix = reallocate(
+        token_program.key,
+        token_account.key,
+        payer.key,
+        authority.key,
+        &[],
+        &[ExtensionType::ConfidentialTransferAccount],
+    )?;
+    invoke_signed(
+        &realloc_ix,
+        &[
+            token_account.clone(),
+            payer,
+            system_program,
+            authority.clone(),
+        ],
+        signer_seeds,
+    )?;
+
+    // `ConfigureAccount` with its proof in a context state account (offset 0).
+    // The instruction builder in spl-token-2022 is host-only, so the data is encoded here:
+    // decryptable_zero_balance (36) || maximum_pending_balance_credit_counter (u64 LE) || offset (i8).
+    let mut data = TokenInstruction::ConfidentialTransferExtension.pack();
+    data.push(ConfidentialTransferInstruction::ConfigureAccount.into());
+    data.extend_from_slice(&decryptable_zero_balance);
+    data.extend_from_slice(&maximum_pending_balance_credit_counter.to_le_bytes());
+    data.push(0);
+    let configure_ix = anchor_lang::solana_program::instruction::Instruction {
+        program_id: *token_program.key,
+        accounts: vec![
+            AccountMeta::new(*token_account.key, false),
+            AccountMeta::new_readonly(*mint.key, false),
+            AccountMeta::new_readonly(*pubkey_validity_proof.key, false),
+            AccountMeta::new_readonly(*authority.key, true),
+        ],
+        data,
+    };
+    invoke_signed(
+        &configure_ix,
+        &[token_account, mint, pubkey_validity_proof, authority],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+/// Moves a program-owned token account's pending confidential balance into its available balance.
+pub fn apply_pending_balance<'info>(
+    token_program: AccountInfo<'info>,
+    token_account: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    expected_pending_balance_credit_counter: u64,
+    new_decryptable_available_balance: [u8; 36],
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let ix = inner_apply_pending_balance(
+        token_program.key,
+        token_account.key,
+        expected_pending_balance_credit_counter,
+        AeCiphertext(new_decryptable_available_balance),
+        authority.key,
+        &[],
+    )?;
+    invoke_signed(&ix, &[token_account, authority], signer_seeds)?;
+    Ok(())
+}
+
+/// Extracts the transfer amount ciphertext encrypted under the destination key:
+/// the Pedersen commitment followed by the destination decrypt handle.
+fn transfer_amount_destination_ciphertext(
+    transfer_amount_ciphertext: &TransferAmountCiphertext,
+) -> ElGamalCiphertext {
+    let bytes = &transfer_amount_ciphertext.0 .0;
+    let mut destination = [0u8; 64];
+    destination[..32].copy_from_slice(&bytes[..32]);
+    destination[32..].copy_from_slice(&bytes[64..96]);
+    ElGamalCiphertext(destination)
+}
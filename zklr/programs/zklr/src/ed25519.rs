@@ -0,0 +1,80 @@
+//
+// Ed25519 signature checks through Instructions-sysvar introspection.
+//
+// The program cannot verify ed25519 signatures itself. Instead, the transaction
+// carries an Ed25519 program instruction right before the program's instruction;
+// the runtime fails the whole transaction if that signature is invalid. The helper
+// below loads the preceding instruction from the Instructions sysvar and checks
+// that it verifies exactly the expected signer and message.
+//
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+
+use crate::ErrorCode;
+
+/// Size of the signature count and padding at the start of the instruction data.
+const HEADER_LEN: usize = 2;
+/// Size of one `Ed25519SignatureOffsets` entry.
+const OFFSETS_LEN: usize = 14;
+const PUBKEY_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+/// Instruction index meaning "this instruction" in the offsets.
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// Checks that the instruction before the current one is an Ed25519 program instruction
+/// verifying a single signature by `signer` over exactly `message`. Fails with `missing` if
+/// there is no such instruction, and with `invalid` if it verifies anything else.
+pub fn verify_preceding_signature(
+    instructions: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+    missing: ErrorCode,
+    invalid: ErrorCode,
+) -> Result<()> {
+    let current_index = load_current_index_checked(instructions)?;
+    if current_index == 0 {
+        return Err(missing.into());
+    }
+    let ix = load_instruction_at_checked(current_index as usize - 1, instructions)?;
+    if ix.program_id != ed25519_program::ID {
+        return Err(missing.into());
+    }
+
+    let data = &ix.data;
+    if data.len() < HEADER_LEN + OFFSETS_LEN || data[0] != 1 {
+        return Err(invalid.into());
+    }
+    let offsets = &data[HEADER_LEN..HEADER_LEN + OFFSETS_LEN];
+    let read_u16 = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]);
+    let signature_offset = read_u16(0) as usize;
+    let signature_ix = read_u16(2);
+    let pubkey_offset = read_u16(4) as usize;
+    let pubkey_ix = read_u16(6);
+    let message_offset = read_u16(8) as usize;
+    let message_len = read_u16(10) as usize;
+    let message_ix = read_u16(12);
+
+    // The signature, key and message must live in the Ed25519 instruction itself, or the
+    // verified data could differ from what is read here.
+    if signature_ix != CURRENT_INSTRUCTION
+        || pubkey_ix != CURRENT_INSTRUCTION
+        || message_ix != CURRENT_INSTRUCTION
+        || data.len() < signature_offset + SIGNATURE_LEN
+    {
+        return Err(invalid.into());
+    }
+    let pubkey = data
+        .get(pubkey_offset..pubkey_offset + PUBKEY_LEN)
+        .ok_or(invalid)?;
+    let signed_message = data
+        .get(message_offset..message_offset + message_len)
+        .ok_or(invalid)?;
+    if pubkey != signer.as_ref() || signed_message != message {
+        return Err(invalid.into());
+    }
+    Ok(())
+}
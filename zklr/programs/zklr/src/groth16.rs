@@ -0,0 +1,166 @@
+//
+// Groth16 verifier over BN254 built on Solana's alt_bn128 syscalls.
+//
+// Points use the big-endian encoding expected by the syscalls (EIP-197):
+// G1 = x || y (64 bytes), G2 = x_c1 || x_c0 || y_c1 || y_c0 (128 bytes).
+//
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::alt_bn128::prelude::{
+    alt_bn128_addition, alt_bn128_multiplication, alt_bn128_pairing,
+};
+
+use crate::{ErrorCode, VerifyingKey};
+
+/// Number of public inputs derived from a trader commitment.
+pub const COMMITMENT_PUBLIC_INPUTS: usize = 2;
+/// Number of public inputs of a priority proof: the commitment, nullifier, trader and
+/// global state.
+pub const PRIORITY_PUBLIC_INPUTS: usize = 8;
+/// Number of public inputs of a pool membership proof: membership root, nullifier and epoch.
+pub const POOL_MEMBERSHIP_PUBLIC_INPUTS: usize = 5;
+
+pub const G1_LEN: usize = 64;
+pub const G2_LEN: usize = 128;
+/// Serialized proof: A (G1) || B (G2) || C (G1).
+pub const PROOF_LEN: usize = G1_LEN + G2_LEN + G1_LEN;
+
+/// BN254 base field modulus q (big-endian), used to negate G1 points.
+const BASE_FIELD_MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x97, 0x81, 0x6a, 0x91, 0x68, 0x71, 0xca, 0x8d, 0x3c, 0x20, 0x8c, 0x16, 0xd8, 0x7c, 0xfd, 0x47,
+];
+
+/// BN254 scalar field modulus r (big-endian). Public inputs must be below it.
+const SCALAR_FIELD_MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x28, 0x33, 0xe8, 0x48, 0x79, 0xb9, 0x70, 0x91, 0x43, 0xe1, 0xf5, 0x93, 0xf0, 0x00, 0x00, 0x01,
+];
+
+pub struct Groth16Proof {
+    pub a: [u8; G1_LEN],
+    pub b: [u8; G2_LEN],
+    pub c: [u8; G1_LEN],
+}
+
+impl Groth16Proof {
+    /// Parses a serialized proof into its A, B and C points.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != PROOF_LEN {
+            return Err(ErrorCode::InvalidZKProof.into());
+        }
+        let mut a = [0u8; G1_LEN];
+        let mut b = [0u8; G2_LEN];
+        let mut c = [0u8; G1_LEN];
+        a.copy_from_slice(&bytes[..G1_LEN]);
+        b.copy_from_slice(&bytes[G1_LEN..G1_LEN + G2_LEN]);
+        c.copy_from_slice(&bytes[G1_LEN + G2_LEN..]);
+        Ok(Self { a, b, c })
+    }
+}
+
+/// Splits a 32-byte commitment into two 128-bit public inputs (high half, low half),
+/// so that each input fits in the scalar field.
+pub fn commitment_public_inputs(commitment: &[u8; 32]) -> [[u8; 32]; COMMITMENT_PUBLIC_INPUTS] {
+    let mut hi = [0u8; 32];
+    let mut lo = [0u8; 32];
+    hi[16..].copy_from_slice(&commitment[..16]);
+    lo[16..].copy_from_slice(&commitment[16..]);
+    [hi, lo]
+}
+
+/// Public inputs of a priority proof: the high and low halves of the commitment, of the
+/// nullifier, of the trader's key and of the global state key. Binding the keys stops a proof
+/// from being replayed by another trader or against another deployment.
+pub fn priority_public_inputs(
+    commitment: &[u8; 32],
+    nullifier: &[u8; 32],
+    trader: &Pubkey,
+    global_state: &Pubkey,
+) -> [[u8; 32]; PRIORITY_PUBLIC_INPUTS] {
+    let [commitment_hi, commitment_lo] = commitment_public_inputs(commitment);
+    let [nullifier_hi, nullifier_lo] = commitment_public_inputs(nullifier);
+    let [trader_hi, trader_lo] = commitment_public_inputs(&trader.to_bytes());
+    let [global_state_hi, global_state_lo] = commitment_public_inputs(&global_state.to_bytes());
+    [
+        commitment_hi,
+        commitment_lo,
+        nullifier_hi,
+        nullifier_lo,
+        trader_hi,
+        trader_lo,
+        global_state_hi,
+        global_state_lo,
+    ]
+}
+
+/// Public inputs of a pool membership proof: the high and low halves of the membership root
+/// and of the nullifier, followed by the epoch.
+pub fn pool_membership_public_inputs(
+    membership_root: &[u8; 32],
+    nullifier: &[u8; 32],
+    epoch: u64,
+) -> [[u8; 32]; POOL_MEMBERSHIP_PUBLIC_INPUTS] {
+    let [root_hi, root_lo] = commitment_public_inputs(membership_root);
+    let [nullifier_hi, nullifier_lo] = commitment_public_inputs(nullifier);
+    let mut epoch_input = [0u8; 32];
+    epoch_input[24..].copy_from_slice(&epoch.to_be_bytes());
+    [root_hi, root_lo, nullifier_hi, nullifier_lo, epoch_input]
+}
+
+/// Checks the Groth16 pairing equation
+/// e(-A, B) * e(alpha, beta) * e(vk_x, gamma) * e(C, delta) == 1.
+/// Returns `Ok(false)` when the pairing check fails and an error when the
+/// proof or public inputs are malformed.
+pub fn verify(proof: &Groth16Proof, public_inputs: &[[u8; 32]], vk: &VerifyingKey) -> Result<bool> {
+    if public_inputs.len() + 1 != vk.ic.len() {
+        return Err(ErrorCode::InvalidVerifyingKey.into());
+    }
+
+    // vk_x = IC[0] + sum(input_i * IC[i + 1])
+    let mut vk_x = vk.ic[0];
+    for (input, ic) in public_inputs.iter().zip(vk.ic.iter().skip(1)) {
+        if input >= &SCALAR_FIELD_MODULUS {
+            return Err(ErrorCode::InvalidZKProof.into());
+        }
+        let product = alt_bn128_multiplication(&[ic.as_ref(), input.as_ref()].concat())
+            .map_err(|_| ErrorCode::InvalidZKProof)?;
+        let sum = alt_bn128_addition(&[vk_x.as_ref(), product.as_slice()].concat())
+            .map_err(|_| ErrorCode::InvalidZKProof)?;
+        vk_x.copy_from_slice(&sum);
+    }
+
+    let pairing_input = [
+        negate_g1(&proof.a).as_ref(),
+        proof.b.as_ref(),
+        vk.alpha_g1.as_ref(),
+        vk.beta_g2.as_ref(),
+        vk_x.as_ref(),
+        vk.gamma_g2.as_ref(),
+        proof.c.as_ref(),
+        vk.delta_g2.as_ref(),
+    ]
+    .concat();
+    let result = alt_bn128_pairing(&pairing_input).map_err(|_| ErrorCode::InvalidZKProof)?;
+    Ok(result.last() == Some(&1))
+}
+
+/// Negates a G1 point by replacing y with q - y. The point at infinity is its own negation.
+fn negate_g1(point: &[u8; G1_LEN]) -> [u8; G1_LEN] {
+    let mut negated = *point;
+    if point.iter().all(|b| *b == 0) {
+        return negated;
+    }
+    let mut borrow = 0i16;
+    for i in (0..32).rev() {
+        let diff = BASE_FIELD_MODULUS[i] as i16 - point[32 + i] as i16 - borrow;
+        if diff < 0 {
+            negated[32 + i] = (diff + 256) as u8;
+            borrow = 1;
+        } else {
+            negated[32 + i] = diff as u8;
+            borrow = 0;
+        }
+    }
+    negated
+}
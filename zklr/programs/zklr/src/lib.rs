@@ -1,9 +1,24 @@
+// Anchor's macros test cfgs, such as `anchor-debug` and `custom-heap`, this crate doesn't declare.
+#![allow(unexpected_cfgs)]
+
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Transfer, Token, TokenAccount};
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::sysvar::instructions as instructions_sysvar;
+use anchor_lang::solana_program::sysvar::slot_hashes;
+use anchor_lang::system_program::{self, Allocate, Assign, CreateAccount, Transfer};
+use anchor_spl::token_2022::spl_token_2022::extension::confidential_transfer::ConfidentialTransferMint;
+use anchor_spl::token_interface::{
+    self, get_mint_extension_data, Burn, Mint, MintTo, Token2022, TokenAccount, TokenInterface,
+    TransferChecked,
+};
+
+mod confidential;
+mod ed25519;
+mod groth16;
 
 declare_id!("8A9hk3goecdw1ymyyXh5EoKYry88C94q2qMrHM9jvxFX");
 
-/// Constants used in the program.
+/// Default protocol parameters, copied into `ProtocolConfig` by `initialize`.
 const PROOF_VALIDITY_PERIOD: i64 = 3600; // 1 hour
 const FEE_PERCENTAGE: u8 = 1; // 1% burn fee
 const MAX_INVALID_PROOFS: u8 = 3;
@@ -12,102 +27,940 @@ const DECAY_PERIOD: i64 = 86400; // 1 day decay period for bandwidth priority
 const LOCKUP_PERIOD: i64 = 3600; // 1 hour lockup before unstaking is allowed
 const REVEAL_DELAY: i64 = 30; // 30 seconds delay before reveal_trade can be called
 const PRIORITY_POOL_BONUS: u8 = 10; // 10% bonus rewards for LPs in priority pools
-const MIN_CONFIDENTIAL_STAKE: u64 = 100; // Minimum stake threshold for bandwidth allocation
 const LIQUIDITY_LOCK_PERIOD: i64 = 86400; // 1 day liquidity lock period for LPs
+const BANDWIDTH_EPOCH_DURATION: i64 = 300; // 5 minute bandwidth allocation epochs
+const BANDWIDTH_CAPACITY: u64 = 1_000; // Transactions per slot shared by each bandwidth epoch
+const AUCTION_BATCH_DURATION: i64 = 30; // 30 second reveal window per batch auction
+
+/// Constants used in the program.
+const MIN_CONFIDENTIAL_STAKE: u64 = 100; // Minimum stake threshold for bandwidth allocation
+const MAX_PUBLIC_INPUTS: usize = 8; // Maximum number of Groth16 public inputs per verifying key
+const MAX_POOL_MEMBERS: usize = 16; // Maximum number of traders in an execution pool
+const COMMITMENT_TREE_DEPTH: usize = 20; // Depth of the commitment tree, for 2^20 commitments
+const ROOT_HISTORY_SIZE: usize = 32; // Number of recent commitment tree roots kept
+const LATENCY_CHALLENGE_VALIDITY_SLOTS: u64 = 150; // Slots a challenge response stays usable
+const MAX_BOOK_ORDERS: usize = 32; // Resting orders per side of an order book
+const ORDER_VERSION: u8 = 1; // Current version of the revealed `Order` encoding
+const ORDER_EXPIRY_BATCHES: u64 = 20; // Batch auctions an unfilled order rests before it expires
+const BPS_DENOMINATOR: u64 = 10_000; // Basis points in 100%
+const REWARD_PER_SHARE_PRECISION: u128 = 1_000_000_000_000; // Fixed-point scale of reward indices
+const MIN_EPOCH_DURATION: i64 = 60; // Shortest reward epoch
+const MAX_EPOCH_DURATION: i64 = 365 * 86400; // Longest reward epoch
+
+/// Pause flags in `GlobalState.paused`, one per instruction class.
+const PAUSE_STAKING: u8 = 1 << 0; // register_trader, stake, unstake, batch_stake_and_verify, claim_trader_rewards
+const PAUSE_VERIFICATION: u8 = 1 << 1; // verify_priority, batch_stake_and_verify, latency challenges
+const PAUSE_BANDWIDTH: u8 = 1 << 2; // bandwidth epochs and tickets, execution pools
+const PAUSE_REVEALS: u8 = 1 << 3; // reveal_trade, clear_auction, cancel_order
+const PAUSE_LIQUIDITY: u8 = 1 << 4; // register_lp, provide_liquidity, withdraw_liquidity, claim_rewards
+const PAUSE_FILL_RECEIPTS: u8 = 1 << 5; // record_fill_receipt
+const PAUSE_REWARDS: u8 = 1 << 6; // advance_epoch
+const PAUSE_ALL: u8 = PAUSE_STAKING
+    | PAUSE_VERIFICATION
+    | PAUSE_BANDWIDTH
+    | PAUSE_REVEALS
+    | PAUSE_LIQUIDITY
+    | PAUSE_FILL_RECEIPTS
+    | PAUSE_REWARDS;
+
+/// PDA seeds.
+const TRADER_SEED: &[u8] = b"trader";
+const LP_SEED: &[u8] = b"lp";
+const STAKE_VAULT_SEED: &[u8] = b"stake_vault";
+const STAKE_AUTHORITY_SEED: &[u8] = b"stake_authority";
+const LIQUIDITY_VAULT_SEED: &[u8] = b"liquidity_vault";
+const LIQUIDITY_AUTHORITY_SEED: &[u8] = b"liquidity_authority";
+const REWARDS_VAULT_SEED: &[u8] = b"rewards_vault";
+const REWARDS_AUTHORITY_SEED: &[u8] = b"rewards_authority";
+const REWARD_SCHEDULE_SEED: &[u8] = b"reward_schedule";
+const VERIFYING_KEY_SEED: &[u8] = b"verifying_key";
+const BANDWIDTH_EPOCH_SEED: &[u8] = b"bandwidth_epoch";
+const BANDWIDTH_TICKET_SEED: &[u8] = b"bandwidth_ticket";
+const EXECUTION_POOL_SEED: &[u8] = b"execution_pool";
+const POOL_ALLOCATION_SEED: &[u8] = b"pool_allocation";
+const TRADER_ALLOCATION_SEED: &[u8] = b"trader_allocation";
+const SLOT_CLAIM_SEED: &[u8] = b"slot_claim";
+const NULLIFIER_SEED: &[u8] = b"nullifier";
+const COMMITMENT_TREE_SEED: &[u8] = b"commitment_tree";
+const LATENCY_ORACLE_SEED: &[u8] = b"latency_oracle";
+const LATENCY_CHALLENGE_SEED: &[u8] = b"latency_challenge";
+const TRADE_VENUE_SEED: &[u8] = b"trade_venue";
+const FILL_RECORD_SEED: &[u8] = b"fill_record";
+const ORDER_BOOK_SEED: &[u8] = b"order_book";
+const AUCTION_CLEARING_SEED: &[u8] = b"auction_clearing";
+const PROTOCOL_CONFIG_SEED: &[u8] = b"protocol_config";
+const TREASURY_SEED: &[u8] = b"treasury";
+const TREASURY_VAULT_SEED: &[u8] = b"treasury_vault";
+const TREASURY_AUTHORITY_SEED: &[u8] = b"treasury_authority";
+
+/// Circuit identifiers for the verifying-key registry.
+const PRIORITY_CIRCUIT_ID: u32 = 0; // Priority proofs checked by verify_priority
+const ORDER_RANGE_CIRCUIT_ID: u32 = 1; // Order range proofs checked by reveal_trade
+const POOL_MEMBERSHIP_CIRCUIT_ID: u32 = 2; // Pool membership proofs checked by claim_execution_slot
 
 #[program]
 pub mod zklr {
     use super::*;
 
-    /// Initializes global state.
+    /// Initializes global state and its protocol configuration with the default parameters.
     pub fn initialize(ctx: Context<Initialize>, admin: Pubkey) -> Result<()> {
         let state = &mut ctx.accounts.global_state;
         state.admin = admin;
+        state.pending_admin = None;
+        state.paused = 0;
         state.total_staked = 0;
         state.total_liquidity = 0;
+        state.lp_reward_per_share = 0;
+        state.trader_reward_per_share = 0;
+        state.lp_reward_rate = 0;
+        state.trader_reward_rate = 0;
+        state.last_reward_timestamp = 0;
+        state.reward_end_timestamp = 0;
+        state.protocol_config = ctx.accounts.protocol_config.key();
+        let protocol_config = &mut ctx.accounts.protocol_config;
+        protocol_config.set_params(&ProtocolConfigParams::default());
+        protocol_config.bump = ctx.bumps.protocol_config;
+        Ok(())
+    }
+
+    /// Admin-only: proposes a new admin. Control only moves once the new admin accepts,
+    /// so a mistyped address cannot take over the protocol.
+    pub fn propose_admin(ctx: Context<UpdateAdmin>, new_admin: Pubkey) -> Result<()> {
+        if new_admin == Pubkey::default() {
+            return Err(ErrorCode::InvalidAdmin.into());
+        }
+        ctx.accounts.global_state.pending_admin = Some(new_admin);
+        emit!(AdminProposed {
+            admin: ctx.accounts.admin.key(),
+            pending_admin: new_admin,
+        });
+        Ok(())
+    }
+
+    /// Completes an admin handover. Must be signed by the proposed admin.
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let state = &mut ctx.accounts.global_state;
+        let old_admin = state.admin;
+        state.admin = ctx.accounts.new_admin.key();
+        state.pending_admin = None;
+        emit!(AdminChanged {
+            old_admin,
+            new_admin: state.admin,
+        });
+        Ok(())
+    }
+
+    /// Admin-only: permanently gives up admin control.
+    /// The admin is set to the default public key, which no one can sign for,
+    /// so protocol parameters, verifying keys and vault settings are frozen for good.
+    pub fn renounce_admin(ctx: Context<UpdateAdmin>) -> Result<()> {
+        let state = &mut ctx.accounts.global_state;
+        let old_admin = state.admin;
+        state.admin = Pubkey::default();
+        state.pending_admin = None;
+        emit!(AdminChanged {
+            old_admin,
+            new_admin: state.admin,
+        });
+        Ok(())
+    }
+
+    /// Admin-only: sets the pause bitmask. Paused instruction classes fail with `ProtocolPaused`;
+    /// admin instructions stay available so the cause can be fixed.
+    pub fn set_paused(ctx: Context<SetPaused>, paused: u8) -> Result<()> {
+        if paused & !PAUSE_ALL != 0 {
+            return Err(ErrorCode::InvalidConfigValue.into());
+        }
+        let state = &mut ctx.accounts.global_state;
+        let old = state.paused;
+        state.paused = paused;
+        emit!(PauseUpdated { old, new: paused });
+        Ok(())
+    }
+
+    /// Admin-only: updates the protocol parameters after checking them against their bounds.
+    /// Emits a `ConfigUpdated` event with the old and new values.
+    pub fn update_config(ctx: Context<UpdateConfig>, params: ProtocolConfigParams) -> Result<()> {
+        params.validate()?;
+        let protocol_config = &mut ctx.accounts.protocol_config;
+        let old = protocol_config.params();
+        protocol_config.set_params(&params);
+        emit!(ConfigUpdated { old, new: params });
+        Ok(())
+    }
+
+    /// Admin-only: creates the program-owned stake and liquidity vaults.
+    /// Both vaults are token accounts at PDAs derived from the global state,
+    /// owned by PDA authorities so that withdrawals can be signed by the program.
+    /// In confidential mode the mint must be a Token-2022 mint with the ConfidentialTransfer extension.
+    pub fn initialize_vaults(ctx: Context<InitializeVaults>, token_mode: TokenMode) -> Result<()> {
+        if token_mode == TokenMode::Confidential {
+            if ctx.accounts.token_program.key() != Token2022::id() {
+                return Err(ErrorCode::InvalidTokenMode.into());
+            }
+            get_mint_extension_data::<ConfidentialTransferMint>(
+                &ctx.accounts.mint.to_account_info(),
+            )
+            .map_err(|_| ErrorCode::InvalidTokenMode)?;
+        }
+        let state = &mut ctx.accounts.global_state;
+        state.token_mint = ctx.accounts.mint.key();
+        state.token_mode = token_mode;
+        state.stake_vault_bump = ctx.bumps.stake_vault;
+        state.stake_authority_bump = ctx.bumps.stake_authority;
+        state.liquidity_vault_bump = ctx.bumps.liquidity_vault;
+        state.liquidity_authority_bump = ctx.bumps.liquidity_authority;
+        Ok(())
+    }
+
+    /// Admin-only: creates the rewards vault that pays out LP rewards in `reward_mint`.
+    /// The vault is funded by transferring reward tokens into it.
+    pub fn initialize_rewards_vault(ctx: Context<InitializeRewardsVault>) -> Result<()> {
+        let state = &mut ctx.accounts.global_state;
+        state.reward_mint = ctx.accounts.reward_mint.key();
+        state.rewards_vault_bump = ctx.bumps.rewards_vault;
+        state.rewards_authority_bump = ctx.bumps.rewards_authority;
+        Ok(())
+    }
+
+    /// Admin-only: creates the reward emission schedule. Epochs are released by `advance_epoch`,
+    /// the first one as soon as the schedule exists.
+    pub fn initialize_reward_schedule(
+        ctx: Context<InitializeRewardSchedule>,
+        params: RewardScheduleParams,
+    ) -> Result<()> {
+        params.validate()?;
+        if params.funding == RewardFunding::Mint
+            && ctx.accounts.reward_mint.mint_authority
+                != COption::Some(ctx.accounts.rewards_authority.key())
+        {
+            return Err(ErrorCode::InvalidRewardFunding.into());
+        }
+        let schedule = &mut ctx.accounts.reward_schedule;
+        schedule.epoch_duration = params.epoch_duration;
+        schedule.initial_emission = params.initial_emission;
+        schedule.decay = params.decay;
+        schedule.lp_share_bps = params.lp_share_bps;
+        schedule.funding = params.funding;
+        schedule.epoch = 0;
+        schedule.next_epoch_start = Clock::get()?.unix_timestamp;
+        schedule.total_released = 0;
+        schedule.carried_emission = 0;
+        schedule.bump = ctx.bumps.reward_schedule;
+        Ok(())
+    }
+
+    /// Releases the next epoch's emission. Permissionless crank.
+    /// The budget is split between LPs and verified traders and streamed to each side over the
+    /// epoch's scheduled window, which starts where the previous one ended even if the crank
+    /// runs late. In `Mint` funding mode the budget is minted into the rewards vault, less the
+    /// rewards returned to the undistributed emission since the last release.
+    pub fn advance_epoch(ctx: Context<AdvanceEpoch>) -> Result<()> {
+        ctx.accounts.global_state.check_not_paused(PAUSE_REWARDS)?;
+        let now = Clock::get()?.unix_timestamp;
+        let schedule = &mut ctx.accounts.reward_schedule;
+        if now < schedule.next_epoch_start {
+            return Err(ErrorCode::EpochNotElapsed.into());
+        }
+        let budget = schedule
+            .emission(schedule.epoch)
+            .checked_add(schedule.carried_emission)
+            .ok_or(ErrorCode::Overflow)?;
+        let lp_budget = budget
+            .checked_mul(schedule.lp_share_bps as u64)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(BPS_DENOMINATOR)
+            .ok_or(ErrorCode::DivisionByZero)?;
+        let trader_budget = budget.checked_sub(lp_budget).ok_or(ErrorCode::Underflow)?;
+        // Budgets are released as whole tokens per second; the remainder is carried into the
+        // next epoch's budget.
+        let duration = schedule.epoch_duration as u64;
+        let lp_reward_rate = lp_budget / duration;
+        let trader_reward_rate = trader_budget / duration;
+        let released = lp_reward_rate
+            .checked_add(trader_reward_rate)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_mul(duration)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let vault_balance = ctx.accounts.rewards_vault.amount;
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.accrue_rewards(now)?;
+        // A pre-funded vault must cover the rewards still owed and the new release.
+        let rewards_owed = global_state
+            .rewards_owed
+            .checked_add(released)
+            .ok_or(ErrorCode::Overflow)?;
+        if schedule.funding == RewardFunding::Vault && vault_balance < rewards_owed {
+            return Err(ErrorCode::InsufficientRewardsVault.into());
+        }
+        global_state.rewards_owed = rewards_owed;
+        // The epoch keeps its scheduled window, so a late crank doesn't push back later epochs.
+        // The time it missed is streamed to the stake and liquidity at the next accrual.
+        let epoch_start = schedule.next_epoch_start;
+        let epoch_end = epoch_start
+            .checked_add(schedule.epoch_duration)
+            .ok_or(ErrorCode::Overflow)?;
+        global_state.lp_reward_rate = lp_reward_rate;
+        global_state.trader_reward_rate = trader_reward_rate;
+        global_state.last_reward_timestamp = epoch_start;
+        global_state.reward_end_timestamp = epoch_end;
+
+        let epoch = schedule.epoch;
+        schedule.epoch = epoch.checked_add(1).ok_or(ErrorCode::Overflow)?;
+        schedule.next_epoch_start = epoch_end;
+        schedule.total_released = schedule
+            .total_released
+            .checked_add(released)
+            .ok_or(ErrorCode::Overflow)?;
+        schedule.carried_emission = budget.checked_sub(released).ok_or(ErrorCode::Underflow)?;
+
+        // Forfeited and unowed rewards stay in the vault and stand in for newly minted ones.
+        let shortfall = rewards_owed.saturating_sub(vault_balance);
+        if schedule.funding == RewardFunding::Mint && shortfall > 0 {
+            let global_state_key = global_state.key();
+            let signer_seeds: &[&[&[u8]]] = &[&[
+                REWARDS_AUTHORITY_SEED,
+                global_state_key.as_ref(),
+                &[global_state.rewards_authority_bump],
+            ]];
+            token_interface::mint_to(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    MintTo {
+                        mint: ctx.accounts.reward_mint.to_account_info(),
+                        to: ctx.accounts.rewards_vault.to_account_info(),
+                        authority: ctx.accounts.rewards_authority.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                shortfall,
+            )?;
+        }
+        emit!(EpochAdvanced {
+            epoch,
+            lp_reward_rate,
+            trader_reward_rate,
+            released,
+        });
+        Ok(())
+    }
+
+    /// Admin-only: enables confidential transfers on the stake or liquidity vault.
+    /// The vault's ElGamal public key is taken from the pubkey validity proof context account.
+    /// Whoever holds the matching secret key can decrypt vault balances and produces the
+    /// proofs for withdrawals out of the vault.
+    pub fn configure_confidential_vault(
+        ctx: Context<ConfigureConfidentialVault>,
+        vault_kind: VaultKind,
+        decryptable_zero_balance: [u8; 36],
+        maximum_pending_balance_credit_counter: u64,
+    ) -> Result<()> {
+        let global_state = &ctx.accounts.global_state;
+        if global_state.token_mode != TokenMode::Confidential {
+            return Err(ErrorCode::InvalidTokenMode.into());
+        }
+        let global_state_key = global_state.key();
+        let authority_bump = global_state.vault_authority_bump(vault_kind);
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            vault_kind.authority_seed(),
+            global_state_key.as_ref(),
+            &[authority_bump],
+        ]];
+        confidential::configure_account(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.mint.to_account_info(),
+            ctx.accounts.pubkey_validity_proof.to_account_info(),
+            ctx.accounts.vault_authority.to_account_info(),
+            ctx.accounts.admin.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            decryptable_zero_balance,
+            maximum_pending_balance_credit_counter,
+            signer_seeds,
+        )
+    }
+
+    /// Admin-only: applies the pending confidential balance of the stake or liquidity vault,
+    /// moving deposits into its available balance.
+    pub fn apply_vault_pending_balance(
+        ctx: Context<ApplyVaultPendingBalance>,
+        vault_kind: VaultKind,
+        expected_pending_balance_credit_counter: u64,
+        new_decryptable_available_balance: [u8; 36],
+    ) -> Result<()> {
+        let global_state = &ctx.accounts.global_state;
+        if global_state.token_mode != TokenMode::Confidential {
+            return Err(ErrorCode::InvalidTokenMode.into());
+        }
+        let global_state_key = global_state.key();
+        let authority_bump = global_state.vault_authority_bump(vault_kind);
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            vault_kind.authority_seed(),
+            global_state_key.as_ref(),
+            &[authority_bump],
+        ]];
+        confidential::apply_pending_balance(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.vault_authority.to_account_info(),
+            expected_pending_balance_credit_counter,
+            new_decryptable_available_balance,
+            signer_seeds,
+        )
+    }
+
+    /// Admin-only: creates the treasury and its token account, which receives slashed stake.
+    /// `insurance_share_bps` of incoming funds go to the insurance reserve, the rest to revenue.
+    pub fn initialize_treasury(
+        ctx: Context<InitializeTreasury>,
+        insurance_share_bps: u16,
+    ) -> Result<()> {
+        if insurance_share_bps as u64 > BPS_DENOMINATOR {
+            return Err(ErrorCode::InvalidConfigValue.into());
+        }
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.insurance_share_bps = insurance_share_bps;
+        treasury.insurance_balance = 0;
+        treasury.revenue_balance = 0;
+        treasury.vault_bump = ctx.bumps.treasury_vault;
+        treasury.authority_bump = ctx.bumps.treasury_authority;
+        treasury.bump = ctx.bumps.treasury;
+        Ok(())
+    }
+
+    /// Admin-only: sets the share of incoming treasury funds credited to the insurance reserve.
+    pub fn set_treasury_split(
+        ctx: Context<SetTreasurySplit>,
+        insurance_share_bps: u16,
+    ) -> Result<()> {
+        if insurance_share_bps as u64 > BPS_DENOMINATOR {
+            return Err(ErrorCode::InvalidConfigValue.into());
+        }
+        let treasury = &mut ctx.accounts.treasury;
+        let old = treasury.insurance_share_bps;
+        treasury.insurance_share_bps = insurance_share_bps;
+        emit!(TreasurySplitUpdated {
+            old,
+            new: insurance_share_bps,
+        });
+        Ok(())
+    }
+
+    /// Admin-only: withdraws from the insurance reserve or protocol revenue of the treasury.
+    pub fn withdraw_treasury(
+        ctx: Context<WithdrawTreasury>,
+        bucket: TreasuryBucket,
+        amount: u64,
+    ) -> Result<()> {
+        let treasury = &mut ctx.accounts.treasury;
+        let balance = match bucket {
+            TreasuryBucket::Insurance => &mut treasury.insurance_balance,
+            TreasuryBucket::Revenue => &mut treasury.revenue_balance,
+        };
+        *balance = balance
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InsufficientTreasuryBalance)?;
+
+        let global_state_key = ctx.accounts.global_state.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            TREASURY_AUTHORITY_SEED,
+            global_state_key.as_ref(),
+            &[treasury.authority_bump],
+        ]];
+        plain_transfer(
+            VaultTransfer {
+                token_program: ctx.accounts.token_program.to_account_info(),
+                from: ctx.accounts.treasury_vault.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.destination.to_account_info(),
+                authority: ctx.accounts.treasury_authority.to_account_info(),
+                decimals: ctx.accounts.mint.decimals,
+                equality_proof: None,
+                ciphertext_validity_proof: None,
+                range_proof: None,
+            },
+            amount,
+            signer_seeds,
+        )?;
+        emit!(TreasuryWithdrawn {
+            bucket,
+            amount,
+            destination: ctx.accounts.destination.key(),
+        });
+        Ok(())
+    }
+
+    /// Admin-only: adds a Groth16 verifying key to the registry under a circuit id and version.
+    /// The key starts out pending and must be activated before proofs are checked against it.
+    /// A key whose `ic` points don't all fit in one transaction is completed with
+    /// `extend_verifying_key`.
+    pub fn add_verifying_key(
+        ctx: Context<AddVerifyingKey>,
+        circuit_id: u32,
+        version: u32,
+        params: VerifyingKeyParams,
+    ) -> Result<()> {
+        let public_inputs = circuit_public_inputs(circuit_id)?;
+        if params.ic.is_empty() || params.ic.len() > public_inputs + 1 {
+            return Err(ErrorCode::InvalidVerifyingKey.into());
+        }
+        let verifying_key = &mut ctx.accounts.verifying_key;
+        verifying_key.circuit_id = circuit_id;
+        verifying_key.version = version;
+        verifying_key.status = VerifyingKeyStatus::Pending;
+        verifying_key.alpha_g1 = params.alpha_g1;
+        verifying_key.beta_g2 = params.beta_g2;
+        verifying_key.gamma_g2 = params.gamma_g2;
+        verifying_key.delta_g2 = params.delta_g2;
+        verifying_key.ic = params.ic;
+        verifying_key.bump = ctx.bumps.verifying_key;
+        Ok(())
+    }
+
+    /// Admin-only: appends `ic` points to a pending verifying key.
+    pub fn extend_verifying_key(ctx: Context<UpdateVerifyingKey>, ic: Vec<[u8; 64]>) -> Result<()> {
+        let verifying_key = &mut ctx.accounts.verifying_key;
+        if verifying_key.status != VerifyingKeyStatus::Pending {
+            return Err(ErrorCode::InvalidVerifyingKeyStatus.into());
+        }
+        let public_inputs = circuit_public_inputs(verifying_key.circuit_id)?;
+        if ic.is_empty() || verifying_key.ic.len() + ic.len() > public_inputs + 1 {
+            return Err(ErrorCode::InvalidVerifyingKey.into());
+        }
+        verifying_key.ic.extend(ic);
+        Ok(())
+    }
+
+    /// Admin-only: activates a pending verifying key so proofs can be checked against it.
+    /// All of the key's `ic` points must have been added.
+    pub fn activate_verifying_key(ctx: Context<UpdateVerifyingKey>) -> Result<()> {
+        let verifying_key = &mut ctx.accounts.verifying_key;
+        if verifying_key.status != VerifyingKeyStatus::Pending {
+            return Err(ErrorCode::InvalidVerifyingKeyStatus.into());
+        }
+        if verifying_key.ic.len() != circuit_public_inputs(verifying_key.circuit_id)? + 1 {
+            return Err(ErrorCode::InvalidVerifyingKey.into());
+        }
+        verifying_key.status = VerifyingKeyStatus::Active;
+        Ok(())
+    }
+
+    /// Admin-only: permanently retires a verifying key.
+    pub fn retire_verifying_key(ctx: Context<UpdateVerifyingKey>) -> Result<()> {
+        let verifying_key = &mut ctx.accounts.verifying_key;
+        if verifying_key.status == VerifyingKeyStatus::Retired {
+            return Err(ErrorCode::InvalidVerifyingKeyStatus.into());
+        }
+        verifying_key.status = VerifyingKeyStatus::Retired;
+        Ok(())
+    }
+
+    /// Admin-only: registers a latency oracle whose signed attestations `verify_priority` and
+    /// `batch_stake_and_verify` accept.
+    pub fn add_latency_oracle(ctx: Context<AddLatencyOracle>, oracle: Pubkey) -> Result<()> {
+        let latency_oracle = &mut ctx.accounts.latency_oracle;
+        latency_oracle.oracle = oracle;
+        latency_oracle.bump = ctx.bumps.latency_oracle;
+        Ok(())
+    }
+
+    /// Admin-only: removes a latency oracle, returning its rent to the admin.
+    pub fn remove_latency_oracle(_ctx: Context<RemoveLatencyOracle>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Posts a latency challenge for the trader, with a nonce derived from the most recent slot
+    /// hash and the slot it was posted in. Answering it with `respond_latency_challenge` measures
+    /// the trader's latency in slots, without trusting an oracle. `responder` is the key of the
+    /// trading node being measured, which must sign the nonce to answer.
+    pub fn issue_latency_challenge(
+        ctx: Context<IssueLatencyChallenge>,
+        responder: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_VERIFICATION)?;
+        let clock = Clock::get()?;
+        // SlotHashes is too large to deserialize; its first entry starts after the u64 length.
+        let slot_hashes = ctx.accounts.slot_hashes.try_borrow_data()?;
+        let recent_hash = slot_hashes
+            .get(16..48)
+            .ok_or(ErrorCode::InvalidChallengeNonce)?;
+        let trader = ctx.accounts.trader.key();
+        let challenge = &mut ctx.accounts.latency_challenge;
+        challenge.trader = trader;
+        challenge.responder = responder;
+        challenge.nonce = anchor_lang::solana_program::hash::hashv(&[
+            recent_hash,
+            trader.as_ref(),
+            &clock.slot.to_le_bytes(),
+        ])
+        .to_bytes();
+        challenge.posted_slot = clock.slot;
+        challenge.responded_slot = None;
+        challenge.bump = ctx.bumps.latency_challenge;
+        Ok(())
+    }
+
+    /// Answers the trader's latency challenge with the responder's Ed25519 signature over the
+    /// nonce, which can't be produced before the challenge is posted. The measured latency is
+    /// the number of slots between the challenge landing and the signed answer landing, so it
+    /// covers reading the nonce, signing it and getting the answer included, at slot
+    /// granularity. An answer in the posting slot is rejected.
+    pub fn respond_latency_challenge(ctx: Context<RespondLatencyChallenge>) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_VERIFICATION)?;
+        let challenge = &mut ctx.accounts.latency_challenge;
+        if challenge.responded_slot.is_some() {
+            return Err(ErrorCode::LatencyChallengeAnswered.into());
+        }
+        ed25519::verify_preceding_signature(
+            &ctx.accounts.instructions,
+            &challenge.responder,
+            &challenge.response_message(&ctx.accounts.global_state.key()),
+            ErrorCode::MissingChallengeResponse,
+            ErrorCode::InvalidChallengeResponse,
+        )?;
+        let slot = Clock::get()?.slot;
+        if slot <= challenge.posted_slot {
+            return Err(ErrorCode::ChallengeResponseTooEarly.into());
+        }
+        challenge.responded_slot = Some(slot);
+        Ok(())
+    }
+
+    /// Admin-only: registers a trading venue whose signed fill receipts are credited as trade
+    /// volume by `record_fill_receipt`.
+    pub fn add_trade_venue(ctx: Context<AddTradeVenue>, venue: Pubkey) -> Result<()> {
+        let trade_venue = &mut ctx.accounts.trade_venue;
+        trade_venue.venue = venue;
+        trade_venue.bump = ctx.bumps.trade_venue;
+        Ok(())
+    }
+
+    /// Admin-only: removes a trading venue, returning its rent to the admin.
+    pub fn remove_trade_venue(_ctx: Context<RemoveTradeVenue>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Credits the volume of a fill receipt signed by a registered venue to the trade volume of
+    /// the trader or LP it names. The receipt's signature is checked through the Ed25519 program
+    /// instruction preceding this one. Each receipt gets a `FillRecord`, so it is only counted
+    /// once. Anyone can submit a receipt; the payer funds its record.
+    pub fn record_fill_receipt(
+        ctx: Context<RecordFillReceipt>,
+        receipt: FillReceipt,
+    ) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_FILL_RECEIPTS)?;
+        let venue = ctx.accounts.trade_venue.venue;
+        ed25519::verify_preceding_signature(
+            &ctx.accounts.instructions,
+            &venue,
+            &receipt.message(&ctx.accounts.global_state.key()),
+            ErrorCode::MissingFillReceiptSignature,
+            ErrorCode::InvalidFillReceipt,
+        )?;
+
+        let trade_volume = match receipt.role {
+            FillRole::Trader => {
+                &mut ctx
+                    .accounts
+                    .trader_account
+                    .as_mut()
+                    .ok_or(ErrorCode::MissingVolumeAccount)?
+                    .trade_volume
+            }
+            FillRole::LiquidityProvider => {
+                &mut ctx
+                    .accounts
+                    .lp_account
+                    .as_mut()
+                    .ok_or(ErrorCode::MissingVolumeAccount)?
+                    .trade_volume
+            }
+        };
+        *trade_volume = trade_volume
+            .checked_add(receipt.volume)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let fill_record = &mut ctx.accounts.fill_record;
+        fill_record.venue = venue;
+        fill_record.receipt_id = receipt.receipt_id;
+        fill_record.participant = receipt.participant;
+        fill_record.volume = receipt.volume;
+        fill_record.recorded_at = Clock::get()?.unix_timestamp;
+        fill_record.bump = ctx.bumps.fill_record;
+
+        emit!(TradeVolumeRecorded {
+            venue,
+            receipt_id: receipt.receipt_id,
+            participant: receipt.participant,
+            role: receipt.role,
+            volume: receipt.volume,
+        });
+        Ok(())
+    }
+
+    /// Admin-only: creates the empty order book of a market, which `reveal_trade` inserts
+    /// revealed orders into. The book keeps the configured batch duration, so later config
+    /// changes don't renumber the batches of its resting orders.
+    pub fn initialize_order_book(ctx: Context<InitializeOrderBook>, market: Pubkey) -> Result<()> {
+        let mut order_book = ctx.accounts.order_book.load_init()?;
+        order_book.market = market;
+        order_book.batch_duration = ctx.accounts.protocol_config.auction_batch_duration;
+        order_book.bump = ctx.bumps.order_book;
+        Ok(())
+    }
+
+    /// Permissionless: clears the batch auction of an order book once the reveal window of
+    /// `batch` has ended. Orders revealed in that window, and earlier orders still resting, trade
+    /// at a single clearing price. Fills are written to the batch's `AuctionClearing` account.
+    pub fn clear_auction(ctx: Context<ClearAuction>, batch: u64) -> Result<()> {
+        ctx.accounts.global_state.check_not_paused(PAUSE_REVEALS)?;
+        let clock = Clock::get()?;
+        let mut order_book = ctx.accounts.order_book.load_mut()?;
+        let current_batch = auction_batch(clock.unix_timestamp, order_book.batch_duration);
+        if batch >= current_batch {
+            return Err(ErrorCode::AuctionWindowOpen.into());
+        }
+        if batch < order_book.next_clear_batch {
+            return Err(ErrorCode::AuctionAlreadyCleared.into());
+        }
+        order_book.remove_expired(current_batch);
+        let (clearing_price, volume) = order_book
+            .clearing_price(batch)?
+            .ok_or(ErrorCode::NoCrossingOrders)?;
+
+        let mut fills = Vec::new();
+        order_book.fill(OrderSide::Bid, batch, clearing_price, volume, &mut fills);
+        order_book.fill(OrderSide::Ask, batch, clearing_price, volume, &mut fills);
+        order_book.next_clear_batch = batch + 1;
+
+        let mut clearing = ctx.accounts.auction_clearing.load_init()?;
+        clearing.order_book = ctx.accounts.order_book.key();
+        clearing.batch = batch;
+        clearing.clearing_price = clearing_price;
+        clearing.volume = volume;
+        clearing.cleared_at = clock.unix_timestamp;
+        clearing.fill_count = fills.len() as u32;
+        clearing.bump = ctx.bumps.auction_clearing;
+        clearing.fills[..fills.len()].copy_from_slice(&fills);
+
+        emit!(AuctionCleared {
+            market: order_book.market,
+            batch,
+            clearing_price,
+            volume,
+            fill_count: clearing.fill_count,
+        });
+        Ok(())
+    }
+
+    /// Removes one of the trader's resting orders from the order book.
+    pub fn cancel_order(ctx: Context<CancelOrder>, side: OrderSide, sequence: u64) -> Result<()> {
+        ctx.accounts.global_state.check_not_paused(PAUSE_REVEALS)?;
+        let trader = ctx.accounts.trader.key();
+        let mut order_book = ctx.accounts.order_book.load_mut()?;
+        let removed = order_book.retain(side, |order| {
+            order.sequence != sequence || order.trader != trader
+        });
+        if removed == 0 {
+            return Err(ErrorCode::OrderNotFound.into());
+        }
+        emit!(OrderCancelled {
+            market: order_book.market,
+            trader,
+            side,
+            sequence,
+        });
+        Ok(())
+    }
+
+    /// Admin-only: creates the empty commitment tree that accepted priority commitments are
+    /// appended to.
+    pub fn initialize_commitment_tree(ctx: Context<InitializeCommitmentTree>) -> Result<()> {
+        let tree = &mut ctx.accounts.commitment_tree;
+        tree.init();
+        tree.bump = ctx.bumps.commitment_tree;
+        Ok(())
+    }
+
+    /// Registers a trader by creating their `TraderAccount` PDA.
+    /// The account is seeded by the global state and the trader's public key.
+    pub fn register_trader(ctx: Context<RegisterTrader>) -> Result<()> {
+        ctx.accounts.global_state.check_not_paused(PAUSE_STAKING)?;
+        let trader_account = &mut ctx.accounts.trader_account;
+        trader_account.trader = ctx.accounts.trader.key();
+        trader_account.global_state = ctx.accounts.global_state.key();
+        trader_account.staked_amount = 0;
+        trader_account.is_verified = false;
+        trader_account.proof_expiry = 0;
+        trader_account.last_proof_update = 0;
+        trader_account.zk_proof_hash = [0u8; 32];
+        trader_account.invalid_proof_attempts = 0;
+        trader_account.commitment = [0u8; 32];
+        trader_account.last_stake_timestamp = 0;
+        trader_account.speed_multiplier = 0;
+        trader_account.trade_volume = 0;
+        trader_account.encrypted_stake = confidential::ZERO_CIPHERTEXT;
+        trader_account.reward_balance = 0;
+        trader_account.reward_debt = 0;
+        trader_account.bump = ctx.bumps.trader_account;
+        Ok(())
+    }
+
+    /// Registers a liquidity provider by creating their `LiquidityAccount` PDA.
+    /// The lock timestamp is stamped at registration so the liquidity lock period applies.
+    pub fn register_lp(ctx: Context<RegisterLp>) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_LIQUIDITY)?;
+        let clock = Clock::get()?;
+        let lp_account = &mut ctx.accounts.lp_account;
+        lp_account.lp = ctx.accounts.lp.key();
+        lp_account.liquidity_provided = 0;
+        lp_account.deposited_amount = 0;
+        lp_account.is_priority_pool = false;
+        lp_account.reward_balance = 0;
+        lp_account.reward_debt = 0;
+        lp_account.lock_timestamp = clock.unix_timestamp;
+        lp_account.trade_volume = 0;
+        lp_account.encrypted_liquidity = confidential::ZERO_CIPHERTEXT;
+        lp_account.bump = ctx.bumps.lp_account;
+        Ok(())
+    }
+
+    /// Admin-only: sets whether a liquidity provider belongs to a priority pool.
+    pub fn set_priority_pool(ctx: Context<SetPriorityPool>, is_priority_pool: bool) -> Result<()> {
+        ctx.accounts.lp_account.is_priority_pool = is_priority_pool;
         Ok(())
     }
 
     /// Confidentially stakes tokens.
-    /// This function uses a confidential transfer so that the staked amount remains hidden.
-    pub fn stake(ctx: Context<Stake>, amount: u64) -> Result<()> {
+    /// Confidential mode is rejected: stake moved with a confidential transfer could not be
+    /// unstaked, as `unstake` can't check an encrypted amount against the encrypted stake.
+    pub fn stake(ctx: Context<Stake>, amount: TransferAmount) -> Result<()> {
+        ctx.accounts.global_state.check_not_paused(PAUSE_STAKING)?;
+        ctx.accounts.global_state.check_plain_mode()?;
         let clock = Clock::get()?;
-        confidential_transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.trader_token_account.to_account_info(),
-                    to: ctx.accounts.stake_vault.to_account_info(),
-                    authority: ctx.accounts.trader.to_account_info(),
-                },
-            ),
-            amount,
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.accrue_rewards(clock.unix_timestamp)?;
+        ctx.accounts
+            .trader_account
+            .settle_rewards(global_state, clock.unix_timestamp)?;
+        let moved = confidential_transfer(
+            ctx.accounts.global_state.token_mode,
+            VaultTransfer {
+                token_program: ctx.accounts.token_program.to_account_info(),
+                from: ctx.accounts.trader_token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.stake_vault.to_account_info(),
+                authority: ctx.accounts.trader.to_account_info(),
+                decimals: ctx.accounts.mint.decimals,
+                equality_proof: ctx.accounts.equality_proof.clone(),
+                ciphertext_validity_proof: ctx.accounts.ciphertext_validity_proof.clone(),
+                range_proof: ctx.accounts.range_proof.clone(),
+            },
+            &amount,
+            &[],
         )?;
 
         let trader_account = &mut ctx.accounts.trader_account;
-        trader_account.staked_amount = trader_account
-            .staked_amount
-            .checked_add(amount)
-            .ok_or(ErrorCode::Overflow)?;
+        match moved {
+            MovedAmount::Plain(amount) => {
+                trader_account.staked_amount = trader_account
+                    .staked_amount
+                    .checked_add(amount)
+                    .ok_or(ErrorCode::Overflow)?;
+                let global_state = &mut ctx.accounts.global_state;
+                global_state.total_staked = global_state
+                    .total_staked
+                    .checked_add(amount)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+            MovedAmount::Confidential(amount) => {
+                trader_account.encrypted_stake =
+                    confidential::add(&trader_account.encrypted_stake, &amount.destination)?;
+            }
+        }
+        trader_account.reset_reward_debt(ctx.accounts.global_state.trader_reward_per_share)?;
         trader_account.last_stake_timestamp = clock.unix_timestamp;
-        let global_state = &mut ctx.accounts.global_state;
-        global_state.total_staked = global_state
-            .total_staked
-            .checked_add(amount)
-            .ok_or(ErrorCode::Overflow)?;
         Ok(())
     }
 
     /// Verifies a trader’s zero-knowledge proof.
-    /// Stores a hash of the proof and commitment for delayed reveal,
+    /// Checks the Groth16 proof against the active verifying key for `vk_version`
+    /// of the priority circuit, consumes the proof's nullifier,
+    /// stores a hash of the proof and commitment for delayed reveal,
     /// burns a fee from the confidential stake, and computes a speed multiplier.
+    /// As in `reveal_trade`, an invalid proof is recorded against the trader without failing
+    /// the transaction or consuming the nullifier, so that `slash_trader` can act on it.
     pub fn verify_priority(
         ctx: Context<VerifyPriority>,
         zk_proof: Vec<u8>,
         commitment: [u8; 32],
-        latency: u64, // lower latency => higher speed multiplier
+        nullifier: [u8; 32],
+        latency: LatencySource, // lower latency => higher speed multiplier
+        _vk_version: u32,
     ) -> Result<()> {
-        if zk_proof.len() < 10 {
-            let trader_account = &mut ctx.accounts.trader_account;
-            trader_account.invalid_proof_attempts = trader_account
-                .invalid_proof_attempts
-                .checked_add(1)
-                .ok_or(ErrorCode::Overflow)?;
-            if trader_account.invalid_proof_attempts >= MAX_INVALID_PROOFS {
-                let slash_amount = trader_account
-                    .staked_amount
-                    .checked_mul(SLASH_PERCENTAGE as u64)
-                    .ok_or(ErrorCode::Overflow)?
-                    .checked_div(100)
-                    .ok_or(ErrorCode::Underflow)?;
-                trader_account.staked_amount = trader_account
-                    .staked_amount
-                    .checked_sub(slash_amount)
-                    .ok_or(ErrorCode::Underflow)?;
-                ctx.accounts.global_state.total_staked = ctx
-                    .accounts
-                    .global_state
-                    .total_staked
-                    .checked_sub(slash_amount)
-                    .ok_or(ErrorCode::Underflow)?;
-                trader_account.invalid_proof_attempts = 0;
-            }
-            return Err(ErrorCode::InvalidZKProof.into());
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_VERIFICATION)?;
+        if !ctx.accounts.proof_nullifier.data_is_empty() {
+            return Err(ErrorCode::ProofReplayed.into());
         }
+        let clock = Clock::get()?;
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.accrue_rewards(clock.unix_timestamp)?;
+        ctx.accounts
+            .trader_account
+            .settle_rewards(global_state, clock.unix_timestamp)?;
+        let public_inputs = groth16::priority_public_inputs(
+            &commitment,
+            &nullifier,
+            &ctx.accounts.trader.key(),
+            &ctx.accounts.global_state.key(),
+        );
+        if !matches!(
+            verify_zk_proof(&zk_proof, &public_inputs, &ctx.accounts.verifying_key),
+            Ok(true)
+        ) {
+            msg!("Invalid priority proof");
+            return record_invalid_proof(&mut ctx.accounts.trader_account);
+        }
+
+        record_nullifier(
+            PdaAccounts {
+                account: ctx.accounts.proof_nullifier.to_account_info(),
+                payer: ctx.accounts.trader.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &ctx.accounts.global_state.key(),
+            &nullifier,
+            &commitment,
+            ctx.bumps.proof_nullifier,
+        )?;
+
+        ctx.accounts.commitment_tree.append(commitment)?;
 
         // Store the hash of the ZK proof and the commitment.
         let proof_hash = anchor_lang::solana_program::hash::hash(&zk_proof).to_bytes();
         let trader_account = &mut ctx.accounts.trader_account;
         trader_account.zk_proof_hash = proof_hash;
         trader_account.commitment = commitment;
-        let clock = Clock::get()?;
         trader_account.last_proof_update = clock.unix_timestamp;
-        trader_account.proof_expiry = clock.unix_timestamp + PROOF_VALIDITY_PERIOD;
+        trader_account.proof_expiry =
+            clock.unix_timestamp + ctx.accounts.protocol_config.proof_validity_period;
 
-        // Burn a fee portion from the confidential stake.
+        // Burn a fee portion from the stake, out of the stake vault.
+        // Encrypted stakes are not charged, as their amount is unknown to the program.
         let fee = trader_account
             .staked_amount
-            .checked_mul(FEE_PERCENTAGE as u64)
+            .checked_mul(ctx.accounts.protocol_config.fee_percentage as u64)
             .ok_or(ErrorCode::Overflow)?
             .checked_div(100)
             .ok_or(ErrorCode::Underflow)?;
@@ -121,58 +974,120 @@ pub mod zklr {
             .total_staked
             .checked_sub(fee)
             .ok_or(ErrorCode::Underflow)?;
+        burn_stake_fee(
+            &ctx.accounts.token_program,
+            &ctx.accounts.mint,
+            &ctx.accounts.stake_vault,
+            &ctx.accounts.stake_authority,
+            &ctx.accounts.global_state,
+            fee,
+        )?;
+        trader_account.reset_reward_debt(ctx.accounts.global_state.trader_reward_per_share)?;
 
-        // Compute speed multiplier (adaptive rewards).
+        // Compute speed multiplier (adaptive rewards) from the attested or measured latency.
         // Here, a simple formula: multiplier = 1000 / (latency + 1)
+        let latency = resolve_latency(
+            &latency,
+            LatencyAccounts {
+                instructions: &ctx.accounts.instructions,
+                latency_oracle: ctx.accounts.latency_oracle.as_deref(),
+                latency_challenge: ctx.accounts.latency_challenge.as_deref(),
+                global_state: &ctx.accounts.global_state.key(),
+                trader: &ctx.accounts.trader.key(),
+            },
+        )?;
+        let trader_account = &mut ctx.accounts.trader_account;
         trader_account.speed_multiplier = 1000u64
             .checked_div(latency + 1)
             .ok_or(ErrorCode::DivisionByZero)?;
-
-        // Reset invalid proof attempts.
-        trader_account.invalid_proof_attempts = 0;
         Ok(())
     }
 
     /// Batch confidential transaction: stakes, burns fee, verifies ZK proof, and grants priority in one atomic transaction.
+    /// As in `verify_priority`, an invalid proof is recorded against the trader before anything
+    /// is staked or burned, and the transaction succeeds without granting priority.
     pub fn batch_stake_and_verify(
         ctx: Context<BatchStakeAndVerify>,
-        amount: u64,
+        amount: TransferAmount,
         zk_proof: Vec<u8>,
         commitment: [u8; 32],
-        latency: u64,
+        nullifier: [u8; 32],
+        latency: LatencySource,
+        _vk_version: u32,
     ) -> Result<()> {
+        let global_state = &ctx.accounts.global_state;
+        global_state.check_not_paused(PAUSE_STAKING)?;
+        global_state.check_not_paused(PAUSE_VERIFICATION)?;
+        global_state.check_plain_mode()?;
+        if !ctx.accounts.proof_nullifier.data_is_empty() {
+            return Err(ErrorCode::ProofReplayed.into());
+        }
         let clock = Clock::get()?;
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.accrue_rewards(clock.unix_timestamp)?;
+        ctx.accounts
+            .trader_account
+            .settle_rewards(global_state, clock.unix_timestamp)?;
+
+        // Verify the ZK proof before anything is moved.
+        let public_inputs = groth16::priority_public_inputs(
+            &commitment,
+            &nullifier,
+            &ctx.accounts.trader.key(),
+            &ctx.accounts.global_state.key(),
+        );
+        if !matches!(
+            verify_zk_proof(&zk_proof, &public_inputs, &ctx.accounts.verifying_key),
+            Ok(true)
+        ) {
+            msg!("Invalid priority proof");
+            return record_invalid_proof(&mut ctx.accounts.trader_account);
+        }
 
         // Confidentially stake tokens.
-        confidential_transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.trader_token_account.to_account_info(),
-                    to: ctx.accounts.stake_vault.to_account_info(),
-                    authority: ctx.accounts.trader.to_account_info(),
-                },
-            ),
-            amount,
+        let moved = confidential_transfer(
+            ctx.accounts.global_state.token_mode,
+            VaultTransfer {
+                token_program: ctx.accounts.token_program.to_account_info(),
+                from: ctx.accounts.trader_token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.stake_vault.to_account_info(),
+                authority: ctx.accounts.trader.to_account_info(),
+                decimals: ctx.accounts.mint.decimals,
+                equality_proof: ctx.accounts.equality_proof.clone(),
+                ciphertext_validity_proof: ctx.accounts.ciphertext_validity_proof.clone(),
+                range_proof: ctx.accounts.range_proof.clone(),
+            },
+            &amount,
+            &[],
         )?;
 
         let trader_account = &mut ctx.accounts.trader_account;
-        trader_account.staked_amount = trader_account
-            .staked_amount
-            .checked_add(amount)
-            .ok_or(ErrorCode::Overflow)?;
+        match moved {
+            MovedAmount::Plain(amount) => {
+                trader_account.staked_amount = trader_account
+                    .staked_amount
+                    .checked_add(amount)
+                    .ok_or(ErrorCode::Overflow)?;
+                ctx.accounts.global_state.total_staked = ctx
+                    .accounts
+                    .global_state
+                    .total_staked
+                    .checked_add(amount)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+            MovedAmount::Confidential(amount) => {
+                trader_account.encrypted_stake =
+                    confidential::add(&trader_account.encrypted_stake, &amount.destination)?;
+            }
+        }
         trader_account.last_stake_timestamp = clock.unix_timestamp;
-        ctx.accounts.global_state.total_staked = ctx
-            .accounts
-            .global_state
-            .total_staked
-            .checked_add(amount)
-            .ok_or(ErrorCode::Overflow)?;
 
-        // Burn fee.
+        // Burn fee out of the stake vault.
+        // Encrypted stakes are not charged, as their amount is unknown to the program.
         let fee = trader_account
             .staked_amount
-            .checked_mul(FEE_PERCENTAGE as u64)
+            .checked_mul(ctx.accounts.protocol_config.fee_percentage as u64)
             .ok_or(ErrorCode::Overflow)?
             .checked_div(100)
             .ok_or(ErrorCode::Underflow)?;
@@ -186,86 +1101,458 @@ pub mod zklr {
             .total_staked
             .checked_sub(fee)
             .ok_or(ErrorCode::Underflow)?;
+        burn_stake_fee(
+            &ctx.accounts.token_program,
+            &ctx.accounts.mint,
+            &ctx.accounts.stake_vault,
+            &ctx.accounts.stake_authority,
+            &ctx.accounts.global_state,
+            fee,
+        )?;
+        trader_account.reset_reward_debt(ctx.accounts.global_state.trader_reward_per_share)?;
 
-        // Verify the ZK proof (simulate) and store proof hash & commitment.
-        if zk_proof.len() < 10 {
-            trader_account.invalid_proof_attempts = trader_account
-                .invalid_proof_attempts
-                .checked_add(1)
-                .ok_or(ErrorCode::Overflow)?;
-            return Err(ErrorCode::InvalidZKProof.into());
-        }
+        // Store proof hash & commitment.
+        record_nullifier(
+            PdaAccounts {
+                account: ctx.accounts.proof_nullifier.to_account_info(),
+                payer: ctx.accounts.trader.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &ctx.accounts.global_state.key(),
+            &nullifier,
+            &commitment,
+            ctx.bumps.proof_nullifier,
+        )?;
+        ctx.accounts.commitment_tree.append(commitment)?;
+        let trader_account = &mut ctx.accounts.trader_account;
         let proof_hash = anchor_lang::solana_program::hash::hash(&zk_proof).to_bytes();
         trader_account.zk_proof_hash = proof_hash;
         trader_account.commitment = commitment;
         trader_account.last_proof_update = clock.unix_timestamp;
-        trader_account.proof_expiry = clock.unix_timestamp + PROOF_VALIDITY_PERIOD;
+        trader_account.proof_expiry =
+            clock.unix_timestamp + ctx.accounts.protocol_config.proof_validity_period;
 
-        // Compute speed multiplier.
+        // Compute speed multiplier from the attested or measured latency.
+        let latency = resolve_latency(
+            &latency,
+            LatencyAccounts {
+                instructions: &ctx.accounts.instructions,
+                latency_oracle: ctx.accounts.latency_oracle.as_deref(),
+                latency_challenge: ctx.accounts.latency_challenge.as_deref(),
+                global_state: &ctx.accounts.global_state.key(),
+                trader: &ctx.accounts.trader.key(),
+            },
+        )?;
+        let trader_account = &mut ctx.accounts.trader_account;
         trader_account.speed_multiplier = 1000u64
             .checked_div(latency + 1)
             .ok_or(ErrorCode::DivisionByZero)?;
 
         // Mark trader as verified.
         trader_account.is_verified = true;
-        trader_account.invalid_proof_attempts = 0;
+        Ok(())
+    }
+
+    /// Opens registration for the next bandwidth epoch. Permissionless; the caller pays rent.
+    /// The epoch's window and capacity are fixed here so config updates don't affect open epochs.
+    pub fn open_bandwidth_epoch(ctx: Context<OpenBandwidthEpoch>, epoch: u64) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_BANDWIDTH)?;
+        let config = &ctx.accounts.protocol_config;
+        let now = Clock::get()?.unix_timestamp;
+        if epoch != bandwidth_epoch(now, config.bandwidth_epoch_duration) + 1 {
+            return Err(ErrorCode::InvalidBandwidthEpoch.into());
+        }
+        let window_start = (epoch as i64)
+            .checked_mul(config.bandwidth_epoch_duration)
+            .ok_or(ErrorCode::Overflow)?;
+        let bandwidth_epoch = &mut ctx.accounts.bandwidth_epoch;
+        bandwidth_epoch.epoch = epoch;
+        bandwidth_epoch.window_start = window_start;
+        bandwidth_epoch.window_end = window_start + config.bandwidth_epoch_duration;
+        bandwidth_epoch.capacity = config.bandwidth_capacity;
+        bandwidth_epoch.total_priority = 0;
+        bandwidth_epoch.trader_count = 0;
+        bandwidth_epoch.bump = ctx.bumps.bandwidth_epoch;
         Ok(())
     }
 
     /// Allocates network bandwidth (priority) using anonymous execution pools.
     /// The effective (anonymous) priority is computed using a decay factor,
     /// the confidential staked amount, the speed multiplier, and the trader’s confidential trade volume.
-    pub fn allocate_bandwidth(ctx: Context<AllocateBandwidth>) -> Result<()> {
+    /// The result is written to the trader's `BandwidthTicket` and added to the epoch's total;
+    /// registration for an epoch closes when its window starts. A trader is allocated at most
+    /// once per epoch, alone or through an execution pool.
+    pub fn allocate_bandwidth(ctx: Context<AllocateBandwidth>, epoch: u64) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_BANDWIDTH)?;
+        ctx.accounts.global_state.check_plain_mode()?;
+        record_trader_allocation(
+            PdaAccounts {
+                account: ctx.accounts.trader_allocation.to_account_info(),
+                payer: ctx.accounts.trader.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &ctx.accounts.global_state.key(),
+            &ctx.accounts.trader.key(),
+            epoch,
+            ctx.accounts.trader.key(),
+            ctx.bumps.trader_allocation,
+        )?;
         let trader_account = &ctx.accounts.trader_account;
         let clock = Clock::get()?;
-        if clock.unix_timestamp > trader_account.proof_expiry {
+        let window_start = ctx.accounts.bandwidth_epoch.window_start;
+        let window_end = ctx.accounts.bandwidth_epoch.window_end;
+        if clock.unix_timestamp >= window_start {
+            return Err(ErrorCode::BandwidthRegistrationClosed.into());
+        }
+        // The proof must still be valid when the allocation takes effect.
+        if window_start > trader_account.proof_expiry {
             return Err(ErrorCode::ProofExpired.into());
         }
         if !trader_account.is_verified {
             return Err(ErrorCode::TraderNotVerified.into());
         }
-        let elapsed = clock.unix_timestamp - trader_account.last_proof_update;
-        let decay_factor = if elapsed < DECAY_PERIOD {
-            DECAY_PERIOD - elapsed
-        } else {
-            0
-        };
-        let base_priority = trader_account
-            .staked_amount
-            .checked_mul(decay_factor as u64)
-            .ok_or(ErrorCode::Overflow)?
-            .checked_div(DECAY_PERIOD as u64)
-            .ok_or(ErrorCode::Underflow)?;
-        // Incorporate the speed multiplier and confidential trade volume.
-        let effective_priority = base_priority
-            .checked_mul(trader_account.speed_multiplier)
-            .ok_or(ErrorCode::Overflow)?
-            .checked_add(trader_account.trade_volume)
+        if trader_account.staked_amount < MIN_CONFIDENTIAL_STAKE {
+            return Err(ErrorCode::InsufficientStake.into());
+        }
+        let effective_priority = trader_account.bandwidth_priority(
+            ctx.accounts.protocol_config.decay_period,
+            clock.unix_timestamp,
+        )?;
+        msg!(
+            "Anonymous bandwidth priority allocated: {}",
+            effective_priority
+        );
+
+        let bandwidth_epoch = &mut ctx.accounts.bandwidth_epoch;
+        bandwidth_epoch.total_priority = bandwidth_epoch
+            .total_priority
+            .checked_add(effective_priority as u128)
+            .ok_or(ErrorCode::Overflow)?;
+        bandwidth_epoch.trader_count = bandwidth_epoch
+            .trader_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let ticket = &mut ctx.accounts.bandwidth_ticket;
+        ticket.trader = trader_account.trader;
+        ticket.epoch = epoch;
+        ticket.priority = effective_priority;
+        ticket.share = 0;
+        ticket.finalized = false;
+        ticket.window_start = window_start;
+        ticket.window_end = window_end;
+        ticket.expires_at = window_end.min(trader_account.proof_expiry);
+        ticket.allocated_slot = clock.slot;
+        ticket.bump = ctx.bumps.bandwidth_ticket;
+        Ok(())
+    }
+
+    /// Sets a ticket's share of its epoch's capacity once registration has closed:
+    /// `capacity * priority / total_priority`, rounded down. Permissionless.
+    pub fn finalize_bandwidth_share(ctx: Context<FinalizeBandwidthShare>) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_BANDWIDTH)?;
+        let bandwidth_epoch = &ctx.accounts.bandwidth_epoch;
+        if Clock::get()?.unix_timestamp < bandwidth_epoch.window_start {
+            return Err(ErrorCode::BandwidthRegistrationOpen.into());
+        }
+        let ticket = &mut ctx.accounts.bandwidth_ticket;
+        ticket.share = bandwidth_epoch.share_of(ticket.priority)?;
+        ticket.finalized = true;
+        Ok(())
+    }
+
+    /// Closes an expired bandwidth ticket and returns its rent to the trader.
+    pub fn close_bandwidth_ticket(ctx: Context<CloseBandwidthTicket>) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_BANDWIDTH)?;
+        if Clock::get()?.unix_timestamp < ctx.accounts.bandwidth_ticket.expires_at {
+            return Err(ErrorCode::BandwidthTicketActive.into());
+        }
+        Ok(())
+    }
+
+    /// Creates an empty execution pool. Permissionless; the creator pays rent.
+    pub fn create_execution_pool(ctx: Context<CreateExecutionPool>, pool_id: u32) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_BANDWIDTH)?;
+        let pool = &mut ctx.accounts.execution_pool;
+        pool.pool_id = pool_id;
+        pool.creator = ctx.accounts.creator.key();
+        pool.members = Vec::new();
+        pool.member_commitments = Vec::new();
+        pool.membership_root = [0u8; 32];
+        pool.bump = ctx.bumps.execution_pool;
+        Ok(())
+    }
+
+    /// Adds the trader to an execution pool under `member_commitment`, a commitment to a secret
+    /// only the trader knows. Slots are later claimed by proving knowledge of the secret behind
+    /// one of the pool's commitments, so claims can't be linked to a member.
+    pub fn join_execution_pool(
+        ctx: Context<JoinExecutionPool>,
+        member_commitment: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_BANDWIDTH)?;
+        let trader = ctx.accounts.trader.key();
+        let pool = &mut ctx.accounts.execution_pool;
+        if pool.members.len() >= MAX_POOL_MEMBERS {
+            return Err(ErrorCode::ExecutionPoolFull.into());
+        }
+        if pool.members.contains(&trader) || pool.member_commitments.contains(&member_commitment) {
+            return Err(ErrorCode::AlreadyPoolMember.into());
+        }
+        pool.members.push(trader);
+        pool.member_commitments.push(member_commitment);
+        pool.membership_root =
+            anchor_lang::solana_program::hash::hashv(&[&pool.membership_root, &member_commitment])
+                .to_bytes();
+        Ok(())
+    }
+
+    /// Removes the trader and their commitment from an execution pool, and rebuilds the
+    /// membership root over the remaining commitments. Allocations already made for the pool
+    /// keep their membership snapshot, so the trader can still claim a slot from them.
+    pub fn leave_execution_pool(ctx: Context<LeaveExecutionPool>) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_BANDWIDTH)?;
+        let trader = ctx.accounts.trader.key();
+        let pool = &mut ctx.accounts.execution_pool;
+        let index = pool
+            .members
+            .iter()
+            .position(|member| *member == trader)
+            .ok_or(ErrorCode::NotPoolMember)?;
+        pool.members.remove(index);
+        pool.member_commitments.remove(index);
+        pool.membership_root =
+            pool.member_commitments
+                .iter()
+                .fold([0u8; 32], |root, commitment| {
+                    anchor_lang::solana_program::hash::hashv(&[&root, commitment]).to_bytes()
+                });
+        Ok(())
+    }
+
+    /// Registers an execution pool for the next bandwidth epoch with the combined priority of its
+    /// members. Permissionless; each member's trader account and allocation marker are passed as
+    /// a pair of remaining accounts, in the pool's member order. Members without a valid proof or
+    /// the minimum stake add nothing, and a member already allocated for the epoch, alone or in
+    /// another pool, fails the registration. The pool's membership is snapshotted for the
+    /// epoch's slot claims.
+    pub fn allocate_pool_bandwidth<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AllocatePoolBandwidth<'info>>,
+        epoch: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_BANDWIDTH)?;
+        ctx.accounts.global_state.check_plain_mode()?;
+        let clock = Clock::get()?;
+        let window_start = ctx.accounts.bandwidth_epoch.window_start;
+        let window_end = ctx.accounts.bandwidth_epoch.window_end;
+        if clock.unix_timestamp >= window_start {
+            return Err(ErrorCode::BandwidthRegistrationClosed.into());
+        }
+        let pool = &ctx.accounts.execution_pool;
+        if pool.members.is_empty() || ctx.remaining_accounts.len() != 2 * pool.members.len() {
+            return Err(ErrorCode::InvalidPoolMembers.into());
+        }
+        let global_state_key = ctx.accounts.global_state.key();
+        let decay_period = ctx.accounts.protocol_config.decay_period;
+        let mut pool_priority: u64 = 0;
+        for (infos, member) in ctx.remaining_accounts.chunks(2).zip(pool.members.iter()) {
+            // The member's trader PDA of this instance, not one registered under another
+            // global state.
+            let trader_account = Account::<TraderAccount>::try_from(&infos[0])?;
+            let trader_pda = Pubkey::create_program_address(
+                &[
+                    TRADER_SEED,
+                    global_state_key.as_ref(),
+                    member.as_ref(),
+                    &[trader_account.bump],
+                ],
+                &crate::ID,
+            )
+            .map_err(|_| ErrorCode::InvalidPoolMembers)?;
+            if infos[0].key() != trader_pda
+                || trader_account.trader != *member
+                || trader_account.global_state != global_state_key
+            {
+                return Err(ErrorCode::InvalidPoolMembers.into());
+            }
+            let (trader_allocation, bump) = Pubkey::find_program_address(
+                &[
+                    TRADER_ALLOCATION_SEED,
+                    global_state_key.as_ref(),
+                    member.as_ref(),
+                    &epoch.to_le_bytes(),
+                ],
+                &crate::ID,
+            );
+            if infos[1].key() != trader_allocation {
+                return Err(ErrorCode::InvalidPoolMembers.into());
+            }
+            record_trader_allocation(
+                PdaAccounts {
+                    account: infos[1].clone(),
+                    payer: ctx.accounts.payer.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &global_state_key,
+                member,
+                epoch,
+                pool.key(),
+                bump,
+            )?;
+            if !trader_account.is_verified
+                || window_start > trader_account.proof_expiry
+                || trader_account.staked_amount < MIN_CONFIDENTIAL_STAKE
+            {
+                continue;
+            }
+            pool_priority = pool_priority
+                .checked_add(trader_account.bandwidth_priority(decay_period, clock.unix_timestamp)?)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+
+        let bandwidth_epoch = &mut ctx.accounts.bandwidth_epoch;
+        bandwidth_epoch.total_priority = bandwidth_epoch
+            .total_priority
+            .checked_add(pool_priority as u128)
+            .ok_or(ErrorCode::Overflow)?;
+        bandwidth_epoch.trader_count = bandwidth_epoch
+            .trader_count
+            .checked_add(1)
             .ok_or(ErrorCode::Overflow)?;
-        msg!("Anonymous bandwidth priority allocated: {}", effective_priority);
+
+        let ticket = &mut ctx.accounts.bandwidth_ticket;
+        ticket.trader = pool.key();
+        ticket.epoch = epoch;
+        ticket.priority = pool_priority;
+        ticket.share = 0;
+        ticket.finalized = false;
+        ticket.window_start = window_start;
+        ticket.window_end = window_end;
+        ticket.expires_at = window_end;
+        ticket.allocated_slot = clock.slot;
+        ticket.bump = ctx.bumps.bandwidth_ticket;
+
+        let allocation = &mut ctx.accounts.pool_allocation;
+        allocation.pool = pool.key();
+        allocation.epoch = epoch;
+        allocation.membership_root = pool.membership_root;
+        allocation.member_count = pool.members.len() as u8;
+        allocation.claimed_count = 0;
+        allocation.bump = ctx.bumps.pool_allocation;
+        Ok(())
+    }
+
+    /// Claims a member's execution slot from the pool's finalized bandwidth share, without
+    /// revealing which member is claiming. The Groth16 proof shows knowledge of the secret behind
+    /// one of the commitments in the epoch's membership snapshot, and that `nullifier` is derived
+    /// from that secret and the epoch, so each member claims at most once per epoch.
+    /// Any signer can submit the claim, e.g. a fresh wallet or a relayer.
+    pub fn claim_execution_slot(
+        ctx: Context<ClaimExecutionSlot>,
+        epoch: u64,
+        nullifier: [u8; 32],
+        zk_proof: Vec<u8>,
+        _vk_version: u32,
+    ) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_BANDWIDTH)?;
+        let ticket = &ctx.accounts.bandwidth_ticket;
+        if !ticket.finalized {
+            return Err(ErrorCode::BandwidthShareNotFinalized.into());
+        }
+        let clock = Clock::get()?;
+        if clock.unix_timestamp >= ticket.window_end {
+            return Err(ErrorCode::ExecutionWindowClosed.into());
+        }
+        let allocation = &mut ctx.accounts.pool_allocation;
+        if allocation.claimed_count >= allocation.member_count {
+            return Err(ErrorCode::PoolSlotsExhausted.into());
+        }
+        let proof = groth16::Groth16Proof::from_bytes(&zk_proof)?;
+        let public_inputs =
+            groth16::pool_membership_public_inputs(&allocation.membership_root, &nullifier, epoch);
+        if !groth16::verify(&proof, &public_inputs, &ctx.accounts.verifying_key)? {
+            return Err(ErrorCode::Groth16VerificationFailed.into());
+        }
+        allocation.claimed_count += 1;
+
+        // The pool's share is split evenly, as claims can't be attributed to a member's priority.
+        let units = ticket.share / allocation.member_count as u64;
+        let claim = &mut ctx.accounts.slot_claim;
+        claim.pool = allocation.pool;
+        claim.epoch = epoch;
+        claim.nullifier = nullifier;
+        claim.claimant = ctx.accounts.claimant.key();
+        claim.units = units;
+        claim.window_start = ticket.window_start;
+        claim.window_end = ticket.window_end;
+        claim.bump = ctx.bumps.slot_claim;
+        emit!(ExecutionSlotClaimed {
+            pool: allocation.pool,
+            epoch,
+            nullifier,
+            claimant: claim.claimant,
+            units,
+        });
         Ok(())
     }
 
-    /// Allows traders to unstake tokens using a confidential withdrawal.
+    /// Allows traders to unstake tokens once the lockup period has elapsed.
+    /// Rejected in confidential mode, where the program cannot check an encrypted amount
+    /// against the trader's stake.
     pub fn unstake(ctx: Context<Unstake>, amount: u64) -> Result<()> {
+        ctx.accounts.global_state.check_not_paused(PAUSE_STAKING)?;
+        ctx.accounts.global_state.check_plain_mode()?;
         let trader_account = &mut ctx.accounts.trader_account;
         let clock = Clock::get()?;
-        if clock.unix_timestamp < trader_account.last_stake_timestamp + LOCKUP_PERIOD {
+        if clock.unix_timestamp
+            < trader_account.last_stake_timestamp + ctx.accounts.protocol_config.lockup_period
+        {
             return Err(ErrorCode::LockupPeriodNotElapsed.into());
         }
         if trader_account.staked_amount < amount {
             return Err(ErrorCode::InsufficientStake.into());
         }
-        confidential_transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.stake_vault.to_account_info(),
-                    to: ctx.accounts.trader_token_account.to_account_info(),
-                    authority: ctx.accounts.stake_authority.to_account_info(),
-                },
-            ),
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.accrue_rewards(clock.unix_timestamp)?;
+        trader_account.settle_rewards(global_state, clock.unix_timestamp)?;
+        let global_state_key = ctx.accounts.global_state.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            STAKE_AUTHORITY_SEED,
+            global_state_key.as_ref(),
+            &[ctx.accounts.global_state.stake_authority_bump],
+        ]];
+        plain_transfer(
+            VaultTransfer {
+                token_program: ctx.accounts.token_program.to_account_info(),
+                from: ctx.accounts.stake_vault.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.trader_token_account.to_account_info(),
+                authority: ctx.accounts.stake_authority.to_account_info(),
+                decimals: ctx.accounts.mint.decimals,
+                equality_proof: None,
+                ciphertext_validity_proof: None,
+                range_proof: None,
+            },
             amount,
+            signer_seeds,
         )?;
         trader_account.staked_amount = trader_account
             .staked_amount
@@ -277,279 +1564,3260 @@ pub mod zklr {
             .total_staked
             .checked_sub(amount)
             .ok_or(ErrorCode::Underflow)?;
-        Ok(())
+        trader_account.reset_reward_debt(ctx.accounts.global_state.trader_reward_per_share)
     }
 
     /// Liquidity providers deposit tokens into a confidential liquidity pool.
-    /// Funds must be locked for a minimum period before rewards can be claimed.
-    /// Additionally, confidential trade volume is tracked for market-making incentives.
-    pub fn provide_liquidity(
-        ctx: Context<ProvideLiquidity>,
-        amount: u64,
-        trade_volume: u64,
-    ) -> Result<()> {
+    /// Each deposit restarts the lock period before liquidity can be withdrawn or rewards claimed.
+    /// Liquidity earns a share of the LP reward rate, and priority pool deposits count with a bonus.
+    /// Confidential mode is rejected, as `withdraw_liquidity` couldn't return the deposit.
+    pub fn provide_liquidity(ctx: Context<ProvideLiquidity>, amount: TransferAmount) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_LIQUIDITY)?;
+        ctx.accounts.global_state.check_plain_mode()?;
+        let clock = Clock::get()?;
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.accrue_rewards(clock.unix_timestamp)?;
+        ctx.accounts
+            .lp_account
+            .settle_rewards(global_state.lp_reward_per_share)?;
+        let moved = confidential_transfer(
+            ctx.accounts.global_state.token_mode,
+            VaultTransfer {
+                token_program: ctx.accounts.token_program.to_account_info(),
+                from: ctx.accounts.lp_token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.liquidity_vault.to_account_info(),
+                authority: ctx.accounts.lp.to_account_info(),
+                decimals: ctx.accounts.mint.decimals,
+                equality_proof: ctx.accounts.equality_proof.clone(),
+                ciphertext_validity_proof: ctx.accounts.ciphertext_validity_proof.clone(),
+                range_proof: ctx.accounts.range_proof.clone(),
+            },
+            &amount,
+            &[],
+        )?;
+        let lp_account = &mut ctx.accounts.lp_account;
+        match moved {
+            MovedAmount::Plain(amount) => {
+                let bonus = if lp_account.is_priority_pool {
+                    amount
+                        .checked_mul(ctx.accounts.protocol_config.priority_pool_bonus as u64)
+                        .ok_or(ErrorCode::Overflow)?
+                        .checked_div(100)
+                        .ok_or(ErrorCode::Underflow)?
+                } else {
+                    0
+                };
+                let total_liquidity = amount.checked_add(bonus).ok_or(ErrorCode::Overflow)?;
+                lp_account.liquidity_provided = lp_account
+                    .liquidity_provided
+                    .checked_add(total_liquidity)
+                    .ok_or(ErrorCode::Overflow)?;
+                lp_account.deposited_amount = lp_account
+                    .deposited_amount
+                    .checked_add(amount)
+                    .ok_or(ErrorCode::Overflow)?;
+                ctx.accounts.global_state.total_liquidity = ctx
+                    .accounts
+                    .global_state
+                    .total_liquidity
+                    .checked_add(total_liquidity)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+            MovedAmount::Confidential(amount) => {
+                lp_account.encrypted_liquidity =
+                    confidential::add(&lp_account.encrypted_liquidity, &amount.destination)?;
+            }
+        }
+        lp_account.reset_reward_debt(ctx.accounts.global_state.lp_reward_per_share)?;
+        lp_account.lock_timestamp = clock.unix_timestamp;
+        Ok(())
+    }
+
+    /// Withdraws deposited tokens from the liquidity vault once the lock period since the last
+    /// deposit has elapsed. The priority pool bonus credited with the withdrawn deposits is
+    /// removed from the LP's liquidity in proportion. Rejected in confidential mode, where the
+    /// program cannot check an encrypted amount against the LP's deposits.
+    pub fn withdraw_liquidity(ctx: Context<WithdrawLiquidity>, amount: u64) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_LIQUIDITY)?;
+        ctx.accounts.global_state.check_plain_mode()?;
+        let lp_account = &mut ctx.accounts.lp_account;
         let clock = Clock::get()?;
-        if clock.unix_timestamp < ctx.accounts.lp_account.lock_timestamp + LIQUIDITY_LOCK_PERIOD {
+        if clock.unix_timestamp
+            < lp_account.lock_timestamp + ctx.accounts.protocol_config.liquidity_lock_period
+        {
             return Err(ErrorCode::LiquidityLockNotElapsed.into());
         }
-        confidential_transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.lp_token_account.to_account_info(),
-                    to: ctx.accounts.liquidity_vault.to_account_info(),
-                    authority: ctx.accounts.lp.to_account_info(),
-                },
-            ),
+        if lp_account.deposited_amount < amount {
+            return Err(ErrorCode::InsufficientLiquidity.into());
+        }
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.accrue_rewards(clock.unix_timestamp)?;
+        lp_account.settle_rewards(global_state.lp_reward_per_share)?;
+        let global_state_key = ctx.accounts.global_state.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            LIQUIDITY_AUTHORITY_SEED,
+            global_state_key.as_ref(),
+            &[ctx.accounts.global_state.liquidity_authority_bump],
+        ]];
+        plain_transfer(
+            VaultTransfer {
+                token_program: ctx.accounts.token_program.to_account_info(),
+                from: ctx.accounts.liquidity_vault.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.lp_token_account.to_account_info(),
+                authority: ctx.accounts.liquidity_authority.to_account_info(),
+                decimals: ctx.accounts.mint.decimals,
+                equality_proof: None,
+                ciphertext_validity_proof: None,
+                range_proof: None,
+            },
             amount,
+            signer_seeds,
         )?;
-        let bonus = if ctx.accounts.lp_account.is_priority_pool {
-            amount
-                .checked_mul(PRIORITY_POOL_BONUS as u64)
-                .ok_or(ErrorCode::Overflow)?
-                .checked_div(100)
-                .ok_or(ErrorCode::Underflow)?
+        // Deposits and their bonus leave together, so that withdrawing every deposit
+        // clears the LP's liquidity.
+        let removed_liquidity = if amount == lp_account.deposited_amount {
+            lp_account.liquidity_provided
         } else {
-            0
+            u64::try_from(
+                (amount as u128)
+                    .checked_mul(lp_account.liquidity_provided as u128)
+                    .ok_or(ErrorCode::Overflow)?
+                    .checked_div(lp_account.deposited_amount as u128)
+                    .ok_or(ErrorCode::DivisionByZero)?,
+            )
+            .map_err(|_| ErrorCode::Overflow)?
         };
-        let total_liquidity = amount.checked_add(bonus).ok_or(ErrorCode::Overflow)?;
-        let lp_account = &mut ctx.accounts.lp_account;
+        lp_account.deposited_amount = lp_account
+            .deposited_amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::Underflow)?;
         lp_account.liquidity_provided = lp_account
             .liquidity_provided
-            .checked_add(total_liquidity)
-            .ok_or(ErrorCode::Overflow)?;
-        lp_account.reward_balance = lp_account
-            .reward_balance
-            .checked_add(bonus)
-            .ok_or(ErrorCode::Overflow)?;
-        // Update confidential trade volume for market-making incentives.
-        lp_account.trade_volume = lp_account
-            .trade_volume
-            .checked_add(trade_volume)
-            .ok_or(ErrorCode::Overflow)?;
+            .checked_sub(removed_liquidity)
+            .ok_or(ErrorCode::Underflow)?;
         ctx.accounts.global_state.total_liquidity = ctx
             .accounts
             .global_state
             .total_liquidity
-            .checked_add(total_liquidity)
-            .ok_or(ErrorCode::Overflow)?;
-        Ok(())
+            .checked_sub(removed_liquidity)
+            .ok_or(ErrorCode::Underflow)?;
+        lp_account.reset_reward_debt(ctx.accounts.global_state.lp_reward_per_share)
+    }
+
+    /// Pays out an LP's accrued rewards from the rewards vault once the liquidity lock has elapsed.
+    /// Rewards accrued since the LP's last settlement are included.
+    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
+        ctx.accounts
+            .global_state
+            .check_not_paused(PAUSE_LIQUIDITY)?;
+        let clock = Clock::get()?;
+        let lp_account = &mut ctx.accounts.lp_account;
+        if clock.unix_timestamp
+            < lp_account.lock_timestamp + ctx.accounts.protocol_config.liquidity_lock_period
+        {
+            return Err(ErrorCode::LiquidityLockNotElapsed.into());
+        }
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.accrue_rewards(clock.unix_timestamp)?;
+        lp_account.settle_rewards(global_state.lp_reward_per_share)?;
+        let amount = lp_account.reward_balance;
+        if ctx.accounts.rewards_vault.amount < amount {
+            return Err(ErrorCode::InsufficientRewardsVault.into());
+        }
+        lp_account.reward_balance = 0;
+        global_state.reduce_rewards_owed(amount)?;
+        pay_rewards(
+            &ctx.accounts.token_program,
+            &ctx.accounts.rewards_vault,
+            &ctx.accounts.reward_mint,
+            &ctx.accounts.lp_reward_token_account,
+            &ctx.accounts.rewards_authority,
+            &ctx.accounts.global_state,
+            amount,
+        )
+    }
+
+    /// Pays out a trader's accrued rewards from the rewards vault.
+    /// Rewards accrue on plain stake and are only credited while the trader is verified.
+    pub fn claim_trader_rewards(ctx: Context<ClaimTraderRewards>) -> Result<()> {
+        ctx.accounts.global_state.check_not_paused(PAUSE_STAKING)?;
+        let clock = Clock::get()?;
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.accrue_rewards(clock.unix_timestamp)?;
+        let trader_account = &mut ctx.accounts.trader_account;
+        trader_account.settle_rewards(global_state, clock.unix_timestamp)?;
+        let amount = trader_account.reward_balance;
+        if ctx.accounts.rewards_vault.amount < amount {
+            return Err(ErrorCode::InsufficientRewardsVault.into());
+        }
+        trader_account.reward_balance = 0;
+        global_state.reduce_rewards_owed(amount)?;
+        pay_rewards(
+            &ctx.accounts.token_program,
+            &ctx.accounts.rewards_vault,
+            &ctx.accounts.reward_mint,
+            &ctx.accounts.trader_reward_token_account,
+            &ctx.accounts.rewards_authority,
+            &ctx.accounts.global_state,
+            amount,
+        )
     }
 
     /// Reveals an encrypted order for fully on-chain confidential order matching.
-    /// This function simulates order range verification using an additional order range proof.
+    /// The order range proof is checked against the active verifying key for `vk_version`
+    /// of the order range circuit. An invalid reveal is recorded against the trader without
+    /// failing the transaction, so that `slash_trader` can act on repeated invalid reveals.
+    /// A valid reveal places the order in its market's order book and spends the commitment.
+    /// Expired orders are removed from the book first, so stale orders can't keep it full.
     pub fn reveal_trade(
         ctx: Context<RevealTrade>,
-        actual_order: Vec<u8>,
+        order: Order,
         order_range_proof: Vec<u8>,
+        _vk_version: u32,
     ) -> Result<()> {
+        ctx.accounts.global_state.check_not_paused(PAUSE_REVEALS)?;
         let trader_account = &mut ctx.accounts.trader_account;
+        if trader_account.commitment == [0u8; 32] {
+            return Err(ErrorCode::NoOrderCommitment.into());
+        }
         let clock = Clock::get()?;
-        if clock.unix_timestamp < trader_account.last_proof_update + REVEAL_DELAY {
+        if clock.unix_timestamp
+            < trader_account.last_proof_update + ctx.accounts.protocol_config.reveal_delay
+        {
             return Err(ErrorCode::RevealTooEarly.into());
         }
-        // Verify the order range proof (simulated check).
-        if order_range_proof.len() < 10 {
-            trader_account.invalid_proof_attempts = trader_account
-                .invalid_proof_attempts
-                .checked_add(1)
-                .ok_or(ErrorCode::Overflow)?;
-            if trader_account.invalid_proof_attempts >= MAX_INVALID_PROOFS {
-                let slash_amount = trader_account
-                    .staked_amount
-                    .checked_mul(SLASH_PERCENTAGE as u64)
-                    .ok_or(ErrorCode::Overflow)?
-                    .checked_div(100)
-                    .ok_or(ErrorCode::Underflow)?;
-                trader_account.staked_amount = trader_account
-                    .staked_amount
-                    .checked_sub(slash_amount)
-                    .ok_or(ErrorCode::Underflow)?;
-                ctx.accounts.global_state.total_staked = ctx
-                    .accounts
-                    .global_state
-                    .total_staked
-                    .checked_sub(slash_amount)
-                    .ok_or(ErrorCode::Underflow)?;
-                trader_account.invalid_proof_attempts = 0;
-            }
-            return Err(ErrorCode::InvalidReveal.into());
+        // Verify the order range proof and that the revealed order matches the commitment.
+        let range_proof_valid = matches!(
+            verify_zk_proof(
+                &order_range_proof,
+                &groth16::commitment_public_inputs(&trader_account.commitment),
+                &ctx.accounts.verifying_key
+            ),
+            Ok(true)
+        );
+        // The commitment is the hash of the order's Borsh encoding.
+        let order_hash = anchor_lang::solana_program::hash::hash(&order.try_to_vec()?).to_bytes();
+        if !range_proof_valid || order_hash != trader_account.commitment {
+            msg!("Invalid reveal");
+            return record_invalid_proof(trader_account);
         }
-        let order_hash = anchor_lang::solana_program::hash::hash(&actual_order).to_bytes();
-        if order_hash != trader_account.commitment {
-            trader_account.invalid_proof_attempts = trader_account
-                .invalid_proof_attempts
-                .checked_add(1)
-                .ok_or(ErrorCode::Overflow)?;
-            if trader_account.invalid_proof_attempts >= MAX_INVALID_PROOFS {
-                let slash_amount = trader_account
-                    .staked_amount
-                    .checked_mul(SLASH_PERCENTAGE as u64)
-                    .ok_or(ErrorCode::Overflow)?
-                    .checked_div(100)
-                    .ok_or(ErrorCode::Underflow)?;
-                trader_account.staked_amount = trader_account
-                    .staked_amount
-                    .checked_sub(slash_amount)
-                    .ok_or(ErrorCode::Underflow)?;
-                ctx.accounts.global_state.total_staked = ctx
-                    .accounts
-                    .global_state
-                    .total_staked
-                    .checked_sub(slash_amount)
-                    .ok_or(ErrorCode::Underflow)?;
-                trader_account.invalid_proof_attempts = 0;
-            }
-            return Err(ErrorCode::InvalidReveal.into());
+        if order.version != ORDER_VERSION {
+            return Err(ErrorCode::UnsupportedOrderVersion.into());
         }
+        if order.price == 0 || order.size == 0 {
+            return Err(ErrorCode::InvalidOrder.into());
+        }
+        // The trader's bandwidth priority at reveal time breaks ties in the batch auction.
+        let priority = trader_account.bandwidth_priority(
+            ctx.accounts.protocol_config.decay_period,
+            clock.unix_timestamp,
+        )?;
+        let mut order_book = ctx.accounts.order_book.load_mut()?;
+        let batch = auction_batch(clock.unix_timestamp, order_book.batch_duration);
+        order_book.remove_expired(batch);
+        let sequence = order_book.insert(
+            order.side,
+            BookOrder {
+                trader: trader_account.trader,
+                commitment: trader_account.commitment,
+                price: order.price,
+                size: order.size,
+                sequence: 0,
+                batch,
+                priority,
+            },
+        )?;
+        // The commitment is spent; the order can't be revealed again.
+        trader_account.commitment = [0u8; 32];
         // If valid, mark the trader as verified.
         trader_account.is_verified = true;
+        emit!(OrderPlaced {
+            market: order.market,
+            trader: trader_account.trader,
+            side: order.side,
+            price: order.price,
+            size: order.size,
+            sequence,
+        });
+        Ok(())
+    }
+
+    /// Permissionless: slashes a trader whose invalid priority proofs and reveals reached
+    /// `max_invalid_proofs`.
+    /// `slash_percentage` of the plain stake moves from the stake vault to the treasury vault,
+    /// where it is split between the insurance reserve and protocol revenue, and the attempt
+    /// count is reset.
+    pub fn slash_trader(ctx: Context<SlashTrader>) -> Result<()> {
+        ctx.accounts.global_state.check_not_paused(PAUSE_STAKING)?;
+        let trader_account = &mut ctx.accounts.trader_account;
+        let global_state = &mut ctx.accounts.global_state;
+        let protocol_config = &ctx.accounts.protocol_config;
+        if trader_account.invalid_proof_attempts < protocol_config.max_invalid_proofs {
+            return Err(ErrorCode::SlashThresholdNotReached.into());
+        }
+        let now = Clock::get()?.unix_timestamp;
+        global_state.accrue_rewards(now)?;
+        trader_account.settle_rewards(global_state, now)?;
+        let slash_amount = trader_account
+            .staked_amount
+            .checked_mul(protocol_config.slash_percentage as u64)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(100)
+            .ok_or(ErrorCode::Underflow)?;
+        trader_account.staked_amount = trader_account
+            .staked_amount
+            .checked_sub(slash_amount)
+            .ok_or(ErrorCode::Underflow)?;
+        global_state.total_staked = global_state
+            .total_staked
+            .checked_sub(slash_amount)
+            .ok_or(ErrorCode::Underflow)?;
+        trader_account.invalid_proof_attempts = 0;
+        trader_account.reset_reward_debt(global_state.trader_reward_per_share)?;
+
+        if slash_amount > 0 {
+            ctx.accounts.treasury.deposit(slash_amount)?;
+            let global_state_key = global_state.key();
+            let signer_seeds: &[&[&[u8]]] = &[&[
+                STAKE_AUTHORITY_SEED,
+                global_state_key.as_ref(),
+                &[global_state.stake_authority_bump],
+            ]];
+            plain_transfer(
+                VaultTransfer {
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                    from: ctx.accounts.stake_vault.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.treasury_vault.to_account_info(),
+                    authority: ctx.accounts.stake_authority.clone(),
+                    decimals: ctx.accounts.mint.decimals,
+                    equality_proof: None,
+                    ciphertext_validity_proof: None,
+                    range_proof: None,
+                },
+                slash_amount,
+                signer_seeds,
+            )?;
+        }
+        emit!(TraderSlashed {
+            trader: trader_account.trader,
+            slashed_amount: slash_amount,
+        });
         Ok(())
     }
 }
 
 //
-// Helper: Confidential Transfer (placeholder for actual confidential token CPI)
+// Helper: index of the bandwidth epoch containing `timestamp`.
 //
-fn confidential_transfer<'info>(
-    ctx: CpiContext<'_, '_, '_, 'info, Transfer<'info>>,
-    amount: u64,
-) -> Result<()> {
-    // In production, call the confidential token transfer CPI so that the amount remains encrypted.
-    token::transfer(ctx, amount)
+fn bandwidth_epoch(timestamp: i64, epoch_duration: i64) -> u64 {
+    (timestamp / epoch_duration) as u64
 }
 
 //
-// Account Structures
+// Helper: index of the batch auction whose reveal window contains `timestamp`.
 //
-
-#[account]
-pub struct GlobalState {
-    pub admin: Pubkey,
-    pub total_staked: u64,
-    pub total_liquidity: u64,
+fn auction_batch(timestamp: i64, batch_duration: i64) -> u64 {
+    (timestamp / batch_duration) as u64
 }
 
-impl GlobalState {
-    const SIZE: usize = 32 + 8 + 8;
+//
+// Helper: number of public inputs of a known circuit.
+//
+fn circuit_public_inputs(circuit_id: u32) -> Result<usize> {
+    match circuit_id {
+        PRIORITY_CIRCUIT_ID => Ok(groth16::PRIORITY_PUBLIC_INPUTS),
+        ORDER_RANGE_CIRCUIT_ID => Ok(groth16::COMMITMENT_PUBLIC_INPUTS),
+        POOL_MEMBERSHIP_CIRCUIT_ID => Ok(groth16::POOL_MEMBERSHIP_PUBLIC_INPUTS),
+        _ => Err(ErrorCode::InvalidVerifyingKey.into()),
+    }
 }
 
-#[account]
-pub struct TraderAccount {
-    pub trader: Pubkey,
-    /// In a true confidential system, this value is stored encrypted.
-    pub staked_amount: u64,
-    pub is_verified: bool,
-    pub proof_expiry: i64,
-    pub last_proof_update: i64,
-    pub zk_proof_hash: [u8; 32],
-    pub invalid_proof_attempts: u8,
-    /// Commitment for encrypted order matching.
-    pub commitment: [u8; 32],
-    pub last_stake_timestamp: i64,
-    /// Adaptive rewards: higher multiplier for faster execution.
-    pub speed_multiplier: u64,
-    /// Confidential trade volume used for market-making incentives.
-    pub trade_volume: u64,
+//
+// Helper: Groth16 proof verification against a stored verifying key.
+//
+fn verify_zk_proof(zk_proof: &[u8], public_inputs: &[[u8; 32]], vk: &VerifyingKey) -> Result<bool> {
+    let proof = groth16::Groth16Proof::from_bytes(zk_proof)?;
+    groth16::verify(&proof, public_inputs, vk)
 }
 
-impl TraderAccount {
-    // 32 + 8 + 1 + 8 + 8 + 32 + 1 + 32 + 8 + 8 + 8 = 138 bytes (plus 8-byte discriminator)
-    const SIZE: usize = 32 + 8 + 1 + 8 + 8 + 32 + 1 + 32 + 8 + 8 + 8;
+//
+// Helper: records an invalid priority proof or reveal against the trader. The attempt must be committed for
+// `slash_trader` to act on it, so callers return `Ok` with it instead of failing the transaction.
+//
+fn record_invalid_proof(trader_account: &mut Account<TraderAccount>) -> Result<()> {
+    trader_account.invalid_proof_attempts = trader_account
+        .invalid_proof_attempts
+        .checked_add(1)
+        .ok_or(ErrorCode::Overflow)?;
+    emit!(InvalidProofRecorded {
+        trader: trader_account.trader,
+        invalid_proof_attempts: trader_account.invalid_proof_attempts,
+    });
+    Ok(())
 }
 
-#[account]
-pub struct LiquidityAccount {
-    pub lp: Pubkey,
-    /// Confidential liquidity provided (encrypted in production).
-    pub liquidity_provided: u64,
-    pub is_priority_pool: bool,
-    pub reward_balance: u64,
-    /// Timestamp when liquidity was locked.
-    pub lock_timestamp: i64,
-    /// Confidential trade volume for market-making incentives.
-    pub trade_volume: u64,
+//
+// Helper: burns a verification fee out of the stake vault, signed by the stake vault authority,
+// so that `total_staked` keeps matching the vault balance.
+//
+fn burn_stake_fee<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    stake_vault: &InterfaceAccount<'info, TokenAccount>,
+    stake_authority: &AccountInfo<'info>,
+    global_state: &Account<'info, GlobalState>,
+    fee: u64,
+) -> Result<()> {
+    if fee == 0 {
+        return Ok(());
+    }
+    let global_state_key = global_state.key();
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        STAKE_AUTHORITY_SEED,
+        global_state_key.as_ref(),
+        &[global_state.stake_authority_bump],
+    ]];
+    token_interface::burn(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Burn {
+                mint: mint.to_account_info(),
+                from: stake_vault.to_account_info(),
+                authority: stake_authority.clone(),
+            },
+            signer_seeds,
+        ),
+        fee,
+    )
 }
 
-impl LiquidityAccount {
-    // 32 + 8 + 1 + 8 + 8 + 8 = 65 bytes (plus 8-byte discriminator)
-    const SIZE: usize = 32 + 8 + 1 + 8 + 8 + 8;
+//
+// Helper: pays rewards out of the rewards vault, signed by the rewards vault authority.
+//
+fn pay_rewards<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    rewards_vault: &InterfaceAccount<'info, TokenAccount>,
+    reward_mint: &InterfaceAccount<'info, Mint>,
+    destination: &InterfaceAccount<'info, TokenAccount>,
+    rewards_authority: &AccountInfo<'info>,
+    global_state: &Account<'info, GlobalState>,
+    amount: u64,
+) -> Result<()> {
+    let global_state_key = global_state.key();
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        REWARDS_AUTHORITY_SEED,
+        global_state_key.as_ref(),
+        &[global_state.rewards_authority_bump],
+    ]];
+    plain_transfer(
+        VaultTransfer {
+            token_program: token_program.to_account_info(),
+            from: rewards_vault.to_account_info(),
+            mint: reward_mint.to_account_info(),
+            to: destination.to_account_info(),
+            authority: rewards_authority.clone(),
+            decimals: reward_mint.decimals,
+            equality_proof: None,
+            ciphertext_validity_proof: None,
+            range_proof: None,
+        },
+        amount,
+        signer_seeds,
+    )
 }
 
 //
-// Instruction Contexts
+// Helper: grows a reward index by `rate * elapsed` rewards shared over `total_weight`.
+// Nothing accrues while the total weight is zero.
 //
-
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(init, payer = admin, space = 8 + GlobalState::SIZE)]
-    pub global_state: Account<'info, GlobalState>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub system_program: Program<'info, System>,
+fn accrue_reward_index(index: u128, rate: u64, elapsed: i64, total_weight: u64) -> Result<u128> {
+    if total_weight == 0 {
+        return Ok(index);
+    }
+    let rewards = (rate as u128)
+        .checked_mul(elapsed as u128)
+        .ok_or(ErrorCode::Overflow)?
+        .checked_mul(REWARD_PER_SHARE_PRECISION)
+        .ok_or(ErrorCode::Overflow)?;
+    index
+        .checked_add(rewards / total_weight as u128)
+        .ok_or_else(|| ErrorCode::Overflow.into())
 }
 
-#[derive(Accounts)]
-pub struct Stake<'info> {
-    #[account(mut)]
-    pub trader: Signer<'info>,
-    #[account(mut)]
-    pub trader_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub stake_vault: Account<'info, TokenAccount>,
-    #[account(mut, has_one = trader)]
-    pub trader_account: Account<'info, TraderAccount>,
-    #[account(mut)]
-    pub global_state: Account<'info, GlobalState>,
-    pub token_program: Program<'info, Token>,
+//
+// Helper: returns the rewards accrued on `weight` since `reward_debt` was last set,
+// and moves `reward_debt` up to the current index.
+//
+fn settle_reward_index(weight: u64, index: u128, reward_debt: &mut u128) -> Result<u64> {
+    let accumulated = (weight as u128)
+        .checked_mul(index)
+        .ok_or(ErrorCode::Overflow)?;
+    let pending = accumulated
+        .checked_sub(*reward_debt)
+        .ok_or(ErrorCode::Underflow)?
+        / REWARD_PER_SHARE_PRECISION;
+    *reward_debt = accumulated;
+    u64::try_from(pending).map_err(|_| ErrorCode::Overflow.into())
+}
+
+//
+// Helper: Confidential Transfer into or out of a program vault.
+// In confidential mode the amount stays encrypted and moves with Token-2022's confidential transfer
+// extension; in SPL mode it falls back to a plain `transfer_checked`.
+// Transfers out of program vaults pass the vault authority's signer seeds, so the token program is
+// invoked with `invoke_signed`.
+//
+struct VaultTransfer<'info> {
+    token_program: AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    decimals: u8,
+    equality_proof: Option<AccountInfo<'info>>,
+    ciphertext_validity_proof: Option<AccountInfo<'info>>,
+    range_proof: Option<AccountInfo<'info>>,
+}
+
+/// Amount moved by `confidential_transfer`.
+enum MovedAmount {
+    Plain(u64),
+    Confidential(Box<confidential::EncryptedAmount>),
+}
+
+fn confidential_transfer(
+    token_mode: TokenMode,
+    accounts: VaultTransfer<'_>,
+    amount: &TransferAmount,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<MovedAmount> {
+    match (token_mode, amount) {
+        (TokenMode::Spl, TransferAmount::Plain(amount)) => {
+            plain_transfer(accounts, *amount, signer_seeds)?;
+            Ok(MovedAmount::Plain(*amount))
+        }
+        (TokenMode::Confidential, TransferAmount::Confidential(args)) => {
+            let (Some(equality_proof), Some(ciphertext_validity_proof), Some(range_proof)) = (
+                accounts.equality_proof,
+                accounts.ciphertext_validity_proof,
+                accounts.range_proof,
+            ) else {
+                return Err(ErrorCode::MissingConfidentialProof.into());
+            };
+            let amount = confidential::transfer(
+                confidential::ConfidentialTransferAccounts {
+                    token_program: accounts.token_program,
+                    source: accounts.from,
+                    mint: accounts.mint,
+                    destination: accounts.to,
+                    authority: accounts.authority,
+                    equality_proof,
+                    ciphertext_validity_proof,
+                    range_proof,
+                },
+                args,
+                signer_seeds,
+            )?;
+            Ok(MovedAmount::Confidential(Box::new(amount)))
+        }
+        _ => Err(ErrorCode::TokenModeMismatch.into()),
+    }
+}
+
+fn plain_transfer(
+    accounts: VaultTransfer<'_>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            accounts.token_program,
+            TransferChecked {
+                from: accounts.from,
+                mint: accounts.mint,
+                to: accounts.to,
+                authority: accounts.authority,
+            },
+            signer_seeds,
+        ),
+        amount,
+        accounts.decimals,
+    )
+}
+
+//
+// Helper: creates a program-owned PDA. Creating it by hand rather than with `init` lets the
+// caller check first whether it already exists, and report that with its own error.
+//
+struct PdaAccounts<'info> {
+    account: AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+}
+
+fn create_pda_account(
+    accounts: &PdaAccounts<'_>,
+    space: usize,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let lamports = Rent::get()?.minimum_balance(space);
+    let current_lamports = accounts.account.lamports();
+    if current_lamports == 0 {
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                accounts.system_program.clone(),
+                CreateAccount {
+                    from: accounts.payer.clone(),
+                    to: accounts.account.clone(),
+                },
+                signer_seeds,
+            ),
+            lamports,
+            space as u64,
+            &crate::ID,
+        )?;
+    } else {
+        // Someone sent lamports to the address beforehand, so it can't be created outright.
+        if lamports > current_lamports {
+            system_program::transfer(
+                CpiContext::new(
+                    accounts.system_program.clone(),
+                    Transfer {
+                        from: accounts.payer.clone(),
+                        to: accounts.account.clone(),
+                    },
+                ),
+                lamports - current_lamports,
+            )?;
+        }
+        system_program::allocate(
+            CpiContext::new_with_signer(
+                accounts.system_program.clone(),
+                Allocate {
+                    account_to_allocate: accounts.account.clone(),
+                },
+                signer_seeds,
+            ),
+            space as u64,
+        )?;
+        system_program::assign(
+            CpiContext::new_with_signer(
+                accounts.system_program.clone(),
+                Assign {
+                    account_to_assign: accounts.account.clone(),
+                },
+                signer_seeds,
+            ),
+            &crate::ID,
+        )?;
+    }
+    Ok(())
+}
+
+//
+// Helper: records the nullifier of an accepted priority proof in its own PDA, seeded by the
+// nullifier. A PDA per nullifier keeps verifications from contending on a shared account. The
+// nullifier is one of the proof's public inputs rather than a hash of the proof bytes, since a
+// Groth16 proof can be re-randomized into different bytes for the same inputs.
+//
+fn record_nullifier(
+    accounts: PdaAccounts<'_>,
+    global_state: &Pubkey,
+    nullifier: &[u8; 32],
+    commitment: &[u8; 32],
+    bump: u8,
+) -> Result<()> {
+    let circuit_id = PRIORITY_CIRCUIT_ID.to_le_bytes();
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        NULLIFIER_SEED,
+        global_state.as_ref(),
+        &circuit_id,
+        nullifier,
+        &[bump],
+    ]];
+    create_pda_account(&accounts, 8 + ProofNullifier::SIZE, signer_seeds)?;
+
+    let record = ProofNullifier {
+        circuit_id: PRIORITY_CIRCUIT_ID,
+        nullifier: *nullifier,
+        commitment: *commitment,
+        consumed_at: Clock::get()?.unix_timestamp,
+        bump,
+    };
+    let mut data = accounts.account.try_borrow_mut_data()?;
+    record.try_serialize(&mut &mut data[..])
+}
+
+//
+// Helper: records that a trader's priority was allocated to a bandwidth epoch, alone or through
+// an execution pool, in a marker PDA seeded by the trader and the epoch. Both allocation paths
+// create the marker, so a trader's priority counts at most once per epoch.
+//
+fn record_trader_allocation(
+    accounts: PdaAccounts<'_>,
+    global_state: &Pubkey,
+    trader: &Pubkey,
+    epoch: u64,
+    allocated_to: Pubkey,
+    bump: u8,
+) -> Result<()> {
+    if accounts.account.owner == &crate::ID {
+        return Err(ErrorCode::BandwidthAlreadyAllocated.into());
+    }
+    let epoch_bytes = epoch.to_le_bytes();
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        TRADER_ALLOCATION_SEED,
+        global_state.as_ref(),
+        trader.as_ref(),
+        &epoch_bytes,
+        &[bump],
+    ]];
+    create_pda_account(&accounts, 8 + TraderAllocation::SIZE, signer_seeds)?;
+
+    let record = TraderAllocation {
+        trader: *trader,
+        epoch,
+        allocated_to,
+        bump,
+    };
+    let mut data = accounts.account.try_borrow_mut_data()?;
+    record.try_serialize(&mut &mut data[..])
+}
+
+//
+// Helper: returns the latency to derive the speed multiplier from. An oracle attestation must
+// be signed by the registered oracle, through the Ed25519 program instruction preceding this
+// one, and not be expired. A challenge must have been answered within the last
+// `LATENCY_CHALLENGE_VALIDITY_SLOTS` slots; its latency is the slots taken to answer.
+//
+struct LatencyAccounts<'a, 'info> {
+    instructions: &'a AccountInfo<'info>,
+    latency_oracle: Option<&'a LatencyOracle>,
+    latency_challenge: Option<&'a LatencyChallenge>,
+    global_state: &'a Pubkey,
+    trader: &'a Pubkey,
+}
+
+fn resolve_latency(source: &LatencySource, accounts: LatencyAccounts<'_, '_>) -> Result<u64> {
+    let clock = Clock::get()?;
+    match source {
+        LatencySource::Oracle(attestation) => {
+            let latency_oracle = accounts
+                .latency_oracle
+                .ok_or(ErrorCode::MissingLatencyAttestation)?;
+            if attestation.expiry < clock.unix_timestamp {
+                return Err(ErrorCode::LatencyAttestationExpired.into());
+            }
+            if attestation.slot > clock.slot {
+                return Err(ErrorCode::InvalidLatencyAttestation.into());
+            }
+            ed25519::verify_preceding_signature(
+                accounts.instructions,
+                &latency_oracle.oracle,
+                &attestation.message(accounts.global_state, accounts.trader),
+                ErrorCode::MissingLatencyAttestation,
+                ErrorCode::InvalidLatencyAttestation,
+            )?;
+            Ok(attestation.latency)
+        }
+        LatencySource::Challenge => {
+            let challenge = accounts
+                .latency_challenge
+                .ok_or(ErrorCode::LatencyChallengeNotAnswered)?;
+            let responded_slot = challenge
+                .responded_slot
+                .ok_or(ErrorCode::LatencyChallengeNotAnswered)?;
+            if clock.slot > responded_slot + LATENCY_CHALLENGE_VALIDITY_SLOTS {
+                return Err(ErrorCode::LatencyChallengeExpired.into());
+            }
+            Ok(responded_slot - challenge.posted_slot)
+        }
+    }
+}
+
+//
+// Helper: parent of two commitment tree nodes, `sha256(left || right)`.
+//
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[left, right]).to_bytes()
+}
+
+//
+// Account Structures
+//
+
+#[account]
+pub struct GlobalState {
+    pub admin: Pubkey,
+    /// Admin proposed by `propose_admin`, until it accepts.
+    pub pending_admin: Option<Pubkey>,
+    /// Bitmask of paused instruction classes (`PAUSE_*`).
+    pub paused: u8,
+    pub total_staked: u64,
+    pub total_liquidity: u64,
+    /// LP rewards accrued per unit of liquidity, scaled by `REWARD_PER_SHARE_PRECISION`.
+    pub lp_reward_per_share: u128,
+    /// Trader rewards accrued per unit of stake, scaled by `REWARD_PER_SHARE_PRECISION`.
+    pub trader_reward_per_share: u128,
+    /// LP rewards accrued per second during the current epoch, shared by all plain liquidity.
+    pub lp_reward_rate: u64,
+    /// Trader rewards accrued per second during the current epoch, shared by all plain stake.
+    pub trader_reward_rate: u64,
+    /// Time up to which rewards have accrued into the reward indices.
+    pub last_reward_timestamp: i64,
+    /// End of the current reward epoch, after which nothing accrues until `advance_epoch`.
+    pub reward_end_timestamp: i64,
+    /// Released rewards not yet paid out, which the rewards vault must cover.
+    pub rewards_owed: u64,
+    /// Protocol parameters account.
+    pub protocol_config: Pubkey,
+    /// Mint of the token held by the stake and liquidity vaults.
+    pub token_mint: Pubkey,
+    /// Whether vault transfers are plain SPL transfers or Token-2022 confidential transfers.
+    pub token_mode: TokenMode,
+    pub stake_vault_bump: u8,
+    pub stake_authority_bump: u8,
+    pub liquidity_vault_bump: u8,
+    pub liquidity_authority_bump: u8,
+    /// Mint of the token paid out of the rewards vault.
+    pub reward_mint: Pubkey,
+    pub rewards_vault_bump: u8,
+    pub rewards_authority_bump: u8,
+}
+
+impl GlobalState {
+    // admin, pending_admin, paused, totals, reward indices, epoch and rewards owed,
+    // protocol_config, token_mint and mode, vault bumps, reward mint and rewards vault bumps
+    const SIZE: usize = (32 + (1 + 32) + 1)
+        + (8 + 8)
+        + (16 + 16 + 8 + 8 + 8 + 8 + 8)
+        + 32
+        + (32 + 1)
+        + (1 + 1 + 1 + 1)
+        + (32 + 1 + 1);
+
+    /// Accrues LP and trader rewards since `last_reward_timestamp` into the reward indices,
+    /// up to the end of the current epoch.
+    fn accrue_rewards(&mut self, now: i64) -> Result<()> {
+        let end = now.min(self.reward_end_timestamp);
+        if end <= self.last_reward_timestamp {
+            return Ok(());
+        }
+        let elapsed = end - self.last_reward_timestamp;
+        // Rewards streamed while nothing is staked or provided aren't owed to anyone.
+        for (rate, total_weight) in [
+            (self.lp_reward_rate, self.total_liquidity),
+            (self.trader_reward_rate, self.total_staked),
+        ] {
+            if total_weight == 0 {
+                let unowed = rate
+                    .checked_mul(elapsed as u64)
+                    .ok_or(ErrorCode::Overflow)?;
+                self.reduce_rewards_owed(unowed)?;
+            }
+        }
+        self.lp_reward_per_share = accrue_reward_index(
+            self.lp_reward_per_share,
+            self.lp_reward_rate,
+            elapsed,
+            self.total_liquidity,
+        )?;
+        self.trader_reward_per_share = accrue_reward_index(
+            self.trader_reward_per_share,
+            self.trader_reward_rate,
+            elapsed,
+            self.total_staked,
+        )?;
+        self.last_reward_timestamp = end;
+        Ok(())
+    }
+
+    /// Takes paid out, unowed or forfeited rewards off `rewards_owed`.
+    fn reduce_rewards_owed(&mut self, amount: u64) -> Result<()> {
+        self.rewards_owed = self
+            .rewards_owed
+            .checked_sub(amount)
+            .ok_or(ErrorCode::Underflow)?;
+        Ok(())
+    }
+
+    fn check_not_paused(&self, flag: u8) -> Result<()> {
+        if self.paused & flag != 0 {
+            return Err(ErrorCode::ProtocolPaused.into());
+        }
+        Ok(())
+    }
+
+    /// Instructions that check amounts against a trader's or LP's balances only support SPL mode.
+    fn check_plain_mode(&self) -> Result<()> {
+        if self.token_mode != TokenMode::Spl {
+            return Err(ErrorCode::ConfidentialModeUnsupported.into());
+        }
+        Ok(())
+    }
+
+    fn vault_bump(&self, vault_kind: VaultKind) -> u8 {
+        match vault_kind {
+            VaultKind::Stake => self.stake_vault_bump,
+            VaultKind::Liquidity => self.liquidity_vault_bump,
+        }
+    }
+
+    fn vault_authority_bump(&self, vault_kind: VaultKind) -> u8 {
+        match vault_kind {
+            VaultKind::Stake => self.stake_authority_bump,
+            VaultKind::Liquidity => self.liquidity_authority_bump,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TokenMode {
+    /// Plain SPL Token or Token-2022 transfers with public amounts.
+    Spl,
+    /// Token-2022 confidential transfers with encrypted amounts.
+    Confidential,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum VaultKind {
+    Stake,
+    Liquidity,
+}
+
+impl VaultKind {
+    fn vault_seed(&self) -> &'static [u8] {
+        match self {
+            VaultKind::Stake => STAKE_VAULT_SEED,
+            VaultKind::Liquidity => LIQUIDITY_VAULT_SEED,
+        }
+    }
+
+    fn authority_seed(&self) -> &'static [u8] {
+        match self {
+            VaultKind::Stake => STAKE_AUTHORITY_SEED,
+            VaultKind::Liquidity => LIQUIDITY_AUTHORITY_SEED,
+        }
+    }
+}
+
+/// Side of an order.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderSide {
+    Bid,
+    Ask,
+}
+
+/// Order revealed by `reveal_trade`. Traders commit to `sha256` of its Borsh encoding.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Order {
+    /// Encoding version, currently `ORDER_VERSION`.
+    pub version: u8,
+    /// Market the order is placed in, which selects the order book.
+    pub market: Pubkey,
+    pub side: OrderSide,
+    /// Limit price, in quote units per base unit.
+    pub price: u64,
+    /// Size, in base units.
+    pub size: u64,
+    /// Random salt, so the commitment doesn't reveal the order.
+    pub salt: [u8; 32],
+}
+
+/// Amount argument of vault transfers; the variant must match the global token mode.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum TransferAmount {
+    Plain(u64),
+    Confidential(ConfidentialTransferArgs),
+}
+
+/// Instruction data of a Token-2022 confidential transfer.
+/// The equality, ciphertext validity and range proofs are passed as context state accounts.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ConfidentialTransferArgs {
+    /// Source's new available balance, encrypted under its authenticated encryption key.
+    pub new_source_decryptable_available_balance: [u8; 36],
+    /// Source decrypt handles of the low and high bits of the transfer amount.
+    pub source_decrypt_handle_lo: [u8; 32],
+    pub source_decrypt_handle_hi: [u8; 32],
+}
+
+#[account]
+pub struct ProtocolConfig {
+    /// Validity period of a priority proof, in seconds.
+    pub proof_validity_period: i64,
+    /// Fee burned from the stake on each priority proof, in percent.
+    pub fee_percentage: u8,
+    /// Invalid priority proofs and reveals before the stake can be slashed.
+    pub max_invalid_proofs: u8,
+    /// Share of the stake slashed on repeated failures, in percent.
+    pub slash_percentage: u8,
+    /// Decay period for bandwidth priority, in seconds.
+    pub decay_period: i64,
+    /// Lockup before unstaking is allowed, in seconds.
+    pub lockup_period: i64,
+    /// Delay before reveal_trade can be called, in seconds.
+    pub reveal_delay: i64,
+    /// Bonus rewards for LPs in priority pools, in percent.
+    pub priority_pool_bonus: u8,
+    /// Liquidity lock period for LPs, in seconds.
+    pub liquidity_lock_period: i64,
+    /// Length of a bandwidth allocation epoch, in seconds.
+    pub bandwidth_epoch_duration: i64,
+    /// Capacity shared by the traders of a bandwidth epoch, in transactions per slot.
+    pub bandwidth_capacity: u64,
+    /// Length of a batch auction's reveal window, in seconds, for order books initialized
+    /// afterwards.
+    pub auction_batch_duration: i64,
+    /// Bump seed of the protocol config PDA.
+    pub bump: u8,
+}
+
+impl ProtocolConfig {
+    // 8 + 1 + 1 + 1 + 8 + 8 + 8 + 1 + 8 + 8 + 8 + 8 + 1 = 69 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 8 + 1 + 1 + 1 + 8 + 8 + 8 + 1 + 8 + 8 + 8 + 8 + 1;
+
+    fn params(&self) -> ProtocolConfigParams {
+        ProtocolConfigParams {
+            proof_validity_period: self.proof_validity_period,
+            fee_percentage: self.fee_percentage,
+            max_invalid_proofs: self.max_invalid_proofs,
+            slash_percentage: self.slash_percentage,
+            decay_period: self.decay_period,
+            lockup_period: self.lockup_period,
+            reveal_delay: self.reveal_delay,
+            priority_pool_bonus: self.priority_pool_bonus,
+            liquidity_lock_period: self.liquidity_lock_period,
+            bandwidth_epoch_duration: self.bandwidth_epoch_duration,
+            bandwidth_capacity: self.bandwidth_capacity,
+            auction_batch_duration: self.auction_batch_duration,
+        }
+    }
+
+    fn set_params(&mut self, params: &ProtocolConfigParams) {
+        self.proof_validity_period = params.proof_validity_period;
+        self.fee_percentage = params.fee_percentage;
+        self.max_invalid_proofs = params.max_invalid_proofs;
+        self.slash_percentage = params.slash_percentage;
+        self.decay_period = params.decay_period;
+        self.lockup_period = params.lockup_period;
+        self.reveal_delay = params.reveal_delay;
+        self.priority_pool_bonus = params.priority_pool_bonus;
+        self.liquidity_lock_period = params.liquidity_lock_period;
+        self.bandwidth_epoch_duration = params.bandwidth_epoch_duration;
+        self.bandwidth_capacity = params.bandwidth_capacity;
+        self.auction_batch_duration = params.auction_batch_duration;
+    }
+}
+
+/// Protocol parameters passed to `update_config`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProtocolConfigParams {
+    pub proof_validity_period: i64,
+    pub fee_percentage: u8,
+    pub max_invalid_proofs: u8,
+    pub slash_percentage: u8,
+    pub decay_period: i64,
+    pub lockup_period: i64,
+    pub reveal_delay: i64,
+    pub priority_pool_bonus: u8,
+    pub liquidity_lock_period: i64,
+    pub bandwidth_epoch_duration: i64,
+    pub bandwidth_capacity: u64,
+    pub auction_batch_duration: i64,
+}
+
+impl Default for ProtocolConfigParams {
+    fn default() -> Self {
+        Self {
+            proof_validity_period: PROOF_VALIDITY_PERIOD,
+            fee_percentage: FEE_PERCENTAGE,
+            max_invalid_proofs: MAX_INVALID_PROOFS,
+            slash_percentage: SLASH_PERCENTAGE,
+            decay_period: DECAY_PERIOD,
+            lockup_period: LOCKUP_PERIOD,
+            reveal_delay: REVEAL_DELAY,
+            priority_pool_bonus: PRIORITY_POOL_BONUS,
+            liquidity_lock_period: LIQUIDITY_LOCK_PERIOD,
+            bandwidth_epoch_duration: BANDWIDTH_EPOCH_DURATION,
+            bandwidth_capacity: BANDWIDTH_CAPACITY,
+            auction_batch_duration: AUCTION_BATCH_DURATION,
+        }
+    }
+}
+
+impl ProtocolConfigParams {
+    /// Checks every parameter against its bounds.
+    fn validate(&self) -> Result<()> {
+        const MINUTE: i64 = 60;
+        const DAY: i64 = 86400;
+        let in_bounds = (MINUTE..=7 * DAY).contains(&self.proof_validity_period)
+            && self.fee_percentage <= 10
+            && (1..=20).contains(&self.max_invalid_proofs)
+            && self.slash_percentage <= 100
+            && (MINUTE..=30 * DAY).contains(&self.decay_period)
+            && (0..=30 * DAY).contains(&self.lockup_period)
+            && (0..=DAY).contains(&self.reveal_delay)
+            && self.priority_pool_bonus <= 100
+            && (0..=365 * DAY).contains(&self.liquidity_lock_period)
+            && (MINUTE..=DAY).contains(&self.bandwidth_epoch_duration)
+            && (1..=1_000_000).contains(&self.bandwidth_capacity)
+            && (1..=10 * MINUTE).contains(&self.auction_batch_duration);
+        if !in_bounds {
+            return Err(ErrorCode::InvalidConfigValue.into());
+        }
+        Ok(())
+    }
+}
+
+#[account]
+pub struct BandwidthEpoch {
+    pub epoch: u64,
+    /// Time window of the epoch, `[window_start, window_end)`. Registration closes at `window_start`.
+    pub window_start: i64,
+    pub window_end: i64,
+    /// Capacity shared by the epoch's traders, in transactions per slot.
+    pub capacity: u64,
+    /// Sum of the priorities of all registered tickets.
+    pub total_priority: u128,
+    /// Number of registered tickets.
+    pub trader_count: u32,
+    /// Bump seed of the bandwidth epoch PDA.
+    pub bump: u8,
+}
+
+impl BandwidthEpoch {
+    // 8 + 8 + 8 + 8 + 16 + 4 + 1 = 53 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 8 + 8 + 8 + 8 + 16 + 4 + 1;
+
+    /// Share of `capacity` for a ticket with `priority`, rounded down so shares never exceed it.
+    fn share_of(&self, priority: u64) -> Result<u64> {
+        if self.total_priority == 0 {
+            return Ok(0);
+        }
+        let share = (self.capacity as u128)
+            .checked_mul(priority as u128)
+            .ok_or(ErrorCode::Overflow)?
+            / self.total_priority;
+        Ok(share as u64)
+    }
+}
+
+#[account]
+pub struct BandwidthTicket {
+    /// The trader, or the execution pool for pool tickets.
+    pub trader: Pubkey,
+    /// Bandwidth epoch the allocation applies to.
+    pub epoch: u64,
+    /// Effective priority computed by `allocate_bandwidth`.
+    pub priority: u64,
+    /// Share of the epoch's capacity, set by `finalize_bandwidth_share`.
+    pub share: u64,
+    /// Whether `share` has been finalized.
+    pub finalized: bool,
+    /// Time window of the epoch, `[window_start, window_end)`.
+    pub window_start: i64,
+    pub window_end: i64,
+    /// End of the epoch, or the expiry of the trader's proof if earlier.
+    pub expires_at: i64,
+    /// Slot in which the allocation was made.
+    pub allocated_slot: u64,
+    /// Bump seed of the ticket PDA.
+    pub bump: u8,
+}
+
+impl BandwidthTicket {
+    // 32 + 8 + 8 + 8 + 1 + 8 + 8 + 8 + 8 + 1 = 90 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 32 + 8 + 8 + 8 + 1 + 8 + 8 + 8 + 8 + 1;
+}
+
+/// Marks a trader as allocated for a bandwidth epoch, by `allocate_bandwidth` or
+/// `allocate_pool_bandwidth`.
+#[account]
+pub struct TraderAllocation {
+    pub trader: Pubkey,
+    pub epoch: u64,
+    /// The trader for a solo allocation, or the execution pool the trader was allocated with.
+    pub allocated_to: Pubkey,
+    /// Bump seed of the trader allocation PDA.
+    pub bump: u8,
+}
+
+impl TraderAllocation {
+    // 32 + 8 + 32 + 1 = 73 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 32 + 8 + 32 + 1;
+}
+
+#[account]
+pub struct ProofNullifier {
+    pub circuit_id: u32,
+    /// Nullifier of the accepted proof.
+    pub nullifier: [u8; 32],
+    /// Commitment of the accepted proof.
+    pub commitment: [u8; 32],
+    /// When the proof was accepted.
+    pub consumed_at: i64,
+    /// Bump seed of the nullifier PDA.
+    pub bump: u8,
+}
+
+impl ProofNullifier {
+    // 4 + 32 + 32 + 8 + 1 = 77 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 4 + 32 + 32 + 8 + 1;
+}
+
+#[account]
+pub struct LatencyOracle {
+    /// Ed25519 key the oracle signs latency attestations with.
+    pub oracle: Pubkey,
+    /// Bump seed of the latency oracle PDA.
+    pub bump: u8,
+}
+
+impl LatencyOracle {
+    // 32 + 1 = 33 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 32 + 1;
+}
+
+/// Latency measured for a trader by a latency oracle.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LatencyAttestation {
+    /// Measured latency; lower latency gives a higher speed multiplier.
+    pub latency: u64,
+    /// Slot the measurement was taken in.
+    pub slot: u64,
+    /// Unix timestamp after which the attestation is no longer accepted.
+    pub expiry: i64,
+}
+
+impl LatencyAttestation {
+    /// Message signed by the oracle:
+    /// `global_state || trader || latency (u64 LE) || slot (u64 LE) || expiry (i64 LE)`.
+    fn message(&self, global_state: &Pubkey, trader: &Pubkey) -> Vec<u8> {
+        [
+            global_state.as_ref(),
+            trader.as_ref(),
+            &self.latency.to_le_bytes(),
+            &self.slot.to_le_bytes(),
+            &self.expiry.to_le_bytes(),
+        ]
+        .concat()
+    }
+}
+
+/// Where the latency behind a trader's speed multiplier comes from.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LatencySource {
+    /// Latency attested by a registered oracle.
+    Oracle(LatencyAttestation),
+    /// Latency measured by the trader's answered `LatencyChallenge`, which is consumed.
+    Challenge,
+}
+
+#[account]
+pub struct LatencyChallenge {
+    pub trader: Pubkey,
+    /// Ed25519 key of the trading node being measured, which signs the answer.
+    pub responder: Pubkey,
+    /// Nonce the responder must sign, derived from the most recent slot hash.
+    pub nonce: [u8; 32],
+    /// Slot the challenge was posted in.
+    pub posted_slot: u64,
+    /// Slot the challenge was answered in, once answered.
+    pub responded_slot: Option<u64>,
+    /// Bump seed of the latency challenge PDA.
+    pub bump: u8,
+}
+
+impl LatencyChallenge {
+    // 32 + 32 + 32 + 8 + (1 + 8) + 1 = 114 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 32 + 32 + 32 + 8 + (1 + 8) + 1;
+
+    /// Message signed by the responder: `global_state || trader || nonce`.
+    fn response_message(&self, global_state: &Pubkey) -> Vec<u8> {
+        [global_state.as_ref(), self.trader.as_ref(), &self.nonce].concat()
+    }
+}
+
+#[account]
+pub struct TradeVenue {
+    /// Ed25519 key the venue signs fill receipts with.
+    pub venue: Pubkey,
+    /// Bump seed of the trade venue PDA.
+    pub bump: u8,
+}
+
+impl TradeVenue {
+    // 32 + 1 = 33 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 32 + 1;
+}
+
+/// Side of a fill a receipt credits volume to.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillRole {
+    Trader,
+    LiquidityProvider,
+}
+
+/// Trade volume signed by a registered venue, for a single fill or a periodic volume report.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FillReceipt {
+    /// Venue-chosen identifier, unique per venue; a receipt is only counted once.
+    pub receipt_id: [u8; 32],
+    /// Wallet of the trader or LP credited with the volume.
+    pub participant: Pubkey,
+    pub role: FillRole,
+    pub volume: u64,
+}
+
+impl FillReceipt {
+    /// Message signed by the venue:
+    /// `global_state || receipt_id || participant || role (u8) || volume (u64 LE)`.
+    fn message(&self, global_state: &Pubkey) -> Vec<u8> {
+        [
+            global_state.as_ref(),
+            &self.receipt_id,
+            self.participant.as_ref(),
+            &[self.role as u8],
+            &self.volume.to_le_bytes(),
+        ]
+        .concat()
+    }
+}
+
+#[account]
+pub struct FillRecord {
+    pub venue: Pubkey,
+    pub receipt_id: [u8; 32],
+    /// Wallet credited with the receipt's volume.
+    pub participant: Pubkey,
+    pub volume: u64,
+    /// When the receipt was recorded.
+    pub recorded_at: i64,
+    /// Bump seed of the fill record PDA.
+    pub bump: u8,
+}
+
+impl FillRecord {
+    // 32 + 32 + 32 + 8 + 8 + 1 = 113 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 32 + 32 + 32 + 8 + 8 + 1;
+}
+
+/// Resting order in an `OrderBook`.
+#[zero_copy]
+pub struct BookOrder {
+    pub trader: Pubkey,
+    /// Commitment the order was revealed from.
+    pub commitment: [u8; 32],
+    pub price: u64,
+    pub size: u64,
+    /// Arrival order within the book, for time priority at the same price.
+    pub sequence: u64,
+    /// Batch auction whose reveal window the order was revealed in.
+    pub batch: u64,
+    /// Trader's bandwidth priority at reveal time, which breaks price ties in the auction.
+    pub priority: u64,
+}
+
+#[account(zero_copy)]
+pub struct OrderBook {
+    pub market: Pubkey,
+    /// Sequence number of the next inserted order.
+    pub next_sequence: u64,
+    /// Earliest batch auction that can still be cleared.
+    pub next_clear_batch: u64,
+    /// Length of a batch auction's reveal window, in seconds.
+    pub batch_duration: i64,
+    pub bid_count: u32,
+    pub ask_count: u32,
+    /// Bump seed of the order book PDA.
+    pub bump: u8,
+    pub _padding: [u8; 7],
+    /// Bids, highest price first; the first `bid_count` entries are used.
+    pub bids: [BookOrder; MAX_BOOK_ORDERS],
+    /// Asks, lowest price first; the first `ask_count` entries are used.
+    pub asks: [BookOrder; MAX_BOOK_ORDERS],
+}
+
+impl OrderBook {
+    // 32 + 8 + 8 + 8 + 4 + 4 + 1 + 7 + 2 * 32 * (32 + 32 + 8 + 8 + 8 + 8 + 8) = 6728 bytes (plus 8-byte discriminator)
+    const SIZE: usize = std::mem::size_of::<OrderBook>();
+
+    /// Inserts an order on one side, behind resting orders at the same or a better price.
+    /// Returns the order's sequence number.
+    fn insert(&mut self, side: OrderSide, mut order: BookOrder) -> Result<u64> {
+        order.sequence = self.next_sequence;
+        let (orders, count) = match side {
+            OrderSide::Bid => (&mut self.bids, &mut self.bid_count),
+            OrderSide::Ask => (&mut self.asks, &mut self.ask_count),
+        };
+        let len = *count as usize;
+        if len == MAX_BOOK_ORDERS {
+            return Err(ErrorCode::OrderBookFull.into());
+        }
+        let position = orders[..len]
+            .iter()
+            .position(|resting| match side {
+                OrderSide::Bid => order.price > resting.price,
+                OrderSide::Ask => order.price < resting.price,
+            })
+            .unwrap_or(len);
+        orders.copy_within(position..len, position + 1);
+        orders[position] = order;
+        *count += 1;
+        self.next_sequence += 1;
+        Ok(order.sequence)
+    }
+
+    /// Keeps the orders on one side for which `keep` returns true, in book order.
+    /// Returns the number of orders removed.
+    fn retain(&mut self, side: OrderSide, keep: impl Fn(&BookOrder) -> bool) -> usize {
+        let (orders, count) = self.side_mut(side);
+        let len = *count as usize;
+        let mut kept = 0;
+        for i in 0..len {
+            if keep(&orders[i]) {
+                orders[kept] = orders[i];
+                kept += 1;
+            }
+        }
+        *count = kept as u32;
+        len - kept
+    }
+
+    /// Removes orders revealed `ORDER_EXPIRY_BATCHES` or more batch auctions before `batch`.
+    fn remove_expired(&mut self, batch: u64) {
+        for side in [OrderSide::Bid, OrderSide::Ask] {
+            self.retain(side, |order| order.batch + ORDER_EXPIRY_BATCHES > batch);
+        }
+    }
+
+    /// Resting orders on one side, with their count.
+    fn side_mut(&mut self, side: OrderSide) -> (&mut [BookOrder; MAX_BOOK_ORDERS], &mut u32) {
+        match side {
+            OrderSide::Bid => (&mut self.bids, &mut self.bid_count),
+            OrderSide::Ask => (&mut self.asks, &mut self.ask_count),
+        }
+    }
+
+    /// Uniform clearing price and volume over orders revealed up to `batch`: the order price
+    /// executing the most volume, then leaving the least unmatched volume at that price, then the
+    /// lowest. `None` if no orders cross.
+    fn clearing_price(&self, batch: u64) -> Result<Option<(u64, u64)>> {
+        let bids = &self.bids[..self.bid_count as usize];
+        let asks = &self.asks[..self.ask_count as usize];
+        let volume_at = |orders: &[BookOrder], crosses: &dyn Fn(u64) -> bool| -> u128 {
+            orders
+                .iter()
+                .filter(|order| order.batch <= batch && crosses(order.price))
+                .map(|order| order.size as u128)
+                .sum()
+        };
+        let mut best: Option<(u128, u128, u64)> = None;
+        for price in bids
+            .iter()
+            .chain(asks)
+            .filter(|order| order.batch <= batch)
+            .map(|order| order.price)
+        {
+            let demand = volume_at(bids, &|bid| bid >= price);
+            let supply = volume_at(asks, &|ask| ask <= price);
+            let volume = demand.min(supply);
+            if volume == 0 {
+                continue;
+            }
+            let imbalance = demand.abs_diff(supply);
+            let better = match best {
+                None => true,
+                Some((best_volume, best_imbalance, best_price)) => {
+                    (
+                        volume,
+                        std::cmp::Reverse(imbalance),
+                        std::cmp::Reverse(price),
+                    ) > (
+                        best_volume,
+                        std::cmp::Reverse(best_imbalance),
+                        std::cmp::Reverse(best_price),
+                    )
+                }
+            };
+            if better {
+                best = Some((volume, imbalance, price));
+            }
+        }
+        best.map(|(volume, _, price)| {
+            u64::try_from(volume)
+                .map(|volume| (price, volume))
+                .map_err(|_| ErrorCode::Overflow.into())
+        })
+        .transpose()
+    }
+
+    /// Fills `volume` on one side at `price`: better prices first, then higher priority, then
+    /// earlier orders. Filled orders leave the book and partial fills keep resting.
+    fn fill(
+        &mut self,
+        side: OrderSide,
+        batch: u64,
+        price: u64,
+        volume: u64,
+        fills: &mut Vec<AuctionFill>,
+    ) {
+        let (orders, count) = self.side_mut(side);
+        let len = *count as usize;
+        let mut queue: Vec<usize> = (0..len)
+            .filter(|&i| {
+                orders[i].batch <= batch
+                    && match side {
+                        OrderSide::Bid => orders[i].price >= price,
+                        OrderSide::Ask => orders[i].price <= price,
+                    }
+            })
+            .collect();
+        queue.sort_by(|&a, &b| {
+            let (a, b) = (&orders[a], &orders[b]);
+            let by_price = match side {
+                OrderSide::Bid => b.price.cmp(&a.price),
+                OrderSide::Ask => a.price.cmp(&b.price),
+            };
+            by_price
+                .then(b.priority.cmp(&a.priority))
+                .then(a.sequence.cmp(&b.sequence))
+        });
+
+        let mut remaining = volume;
+        for i in queue {
+            if remaining == 0 {
+                break;
+            }
+            let size = orders[i].size.min(remaining);
+            orders[i].size -= size;
+            remaining -= size;
+            fills.push(AuctionFill {
+                trader: orders[i].trader,
+                commitment: orders[i].commitment,
+                size,
+                sequence: orders[i].sequence,
+                side: side as u8,
+                _padding: [0; 7],
+            });
+        }
+
+        // Drop filled orders, keeping the rest in book order.
+        self.retain(side, |order| order.size > 0);
+    }
+}
+
+/// Fill of a resting order in a batch auction, at the auction's clearing price.
+#[zero_copy]
+pub struct AuctionFill {
+    pub trader: Pubkey,
+    /// Commitment the filled order was revealed from.
+    pub commitment: [u8; 32],
+    /// Filled size, in base units.
+    pub size: u64,
+    /// Sequence number of the filled order in the order book.
+    pub sequence: u64,
+    /// 0 for a bid, 1 for an ask.
+    pub side: u8,
+    pub _padding: [u8; 7],
+}
+
+#[account(zero_copy)]
+pub struct AuctionClearing {
+    pub order_book: Pubkey,
+    pub batch: u64,
+    /// Uniform price every fill of the batch trades at.
+    pub clearing_price: u64,
+    /// Volume traded on each side, in base units.
+    pub volume: u64,
+    /// When the auction was cleared.
+    pub cleared_at: i64,
+    pub fill_count: u32,
+    /// Bump seed of the auction clearing PDA.
+    pub bump: u8,
+    pub _padding: [u8; 3],
+    /// Fills of both sides; the first `fill_count` entries are used.
+    pub fills: [AuctionFill; 2 * MAX_BOOK_ORDERS],
+}
+
+impl AuctionClearing {
+    // 32 + 8 + 8 + 8 + 8 + 4 + 1 + 3 + 2 * 32 * (32 + 32 + 8 + 8 + 1 + 7) = 5704 bytes (plus 8-byte discriminator)
+    const SIZE: usize = std::mem::size_of::<AuctionClearing>();
+}
+
+#[account]
+pub struct CommitmentTree {
+    /// Number of commitments appended so far; the index of the next leaf.
+    pub next_index: u32,
+    /// Rightmost filled node at each level, used to compute the next root.
+    pub filled_subtrees: [[u8; 32]; COMMITMENT_TREE_DEPTH],
+    /// Root of an empty subtree at each level.
+    pub zeros: [[u8; 32]; COMMITMENT_TREE_DEPTH],
+    /// Ring buffer of the most recent roots; `roots[current_root_index]` is the current root.
+    pub roots: [[u8; 32]; ROOT_HISTORY_SIZE],
+    pub current_root_index: u32,
+    /// Bump seed of the commitment tree PDA.
+    pub bump: u8,
+}
+
+impl CommitmentTree {
+    // 4 + 32 * COMMITMENT_TREE_DEPTH * 2 + 32 * ROOT_HISTORY_SIZE + 4 + 1 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 4 + 32 * COMMITMENT_TREE_DEPTH * 2 + 32 * ROOT_HISTORY_SIZE + 4 + 1;
+
+    /// Resets the tree to empty: every level holds the empty-subtree root.
+    fn init(&mut self) {
+        let mut zero = [0u8; 32];
+        for level in 0..COMMITMENT_TREE_DEPTH {
+            self.zeros[level] = zero;
+            self.filled_subtrees[level] = zero;
+            zero = hash_pair(&zero, &zero);
+        }
+        self.next_index = 0;
+        self.roots = [[0u8; 32]; ROOT_HISTORY_SIZE];
+        self.roots[0] = zero;
+        self.current_root_index = 0;
+    }
+
+    /// Appends a leaf, pushes the new root into the history and emits `CommitmentAppended`.
+    fn append(&mut self, leaf: [u8; 32]) -> Result<()> {
+        let leaf_index = self.next_index;
+        if leaf_index as u64 >= 1u64 << COMMITMENT_TREE_DEPTH {
+            return Err(ErrorCode::CommitmentTreeFull.into());
+        }
+        let mut index = leaf_index;
+        let mut node = leaf;
+        for level in 0..COMMITMENT_TREE_DEPTH {
+            node = if index & 1 == 0 {
+                self.filled_subtrees[level] = node;
+                hash_pair(&node, &self.zeros[level])
+            } else {
+                hash_pair(&self.filled_subtrees[level], &node)
+            };
+            index /= 2;
+        }
+        self.current_root_index = (self.current_root_index + 1) % ROOT_HISTORY_SIZE as u32;
+        self.roots[self.current_root_index as usize] = node;
+        self.next_index = leaf_index + 1;
+        emit!(CommitmentAppended {
+            leaf_index,
+            commitment: leaf,
+            root: node,
+        });
+        Ok(())
+    }
+}
+
+#[account]
+pub struct ExecutionPool {
+    pub pool_id: u32,
+    pub creator: Pubkey,
+    /// Member traders, in join order.
+    pub members: Vec<Pubkey>,
+    /// Commitments to the members' secrets, in the same order as `members`.
+    pub member_commitments: Vec<[u8; 32]>,
+    /// Hash chain over `member_commitments`: `root = sha256(root || commitment)`, from zero.
+    pub membership_root: [u8; 32],
+    /// Bump seed of the execution pool PDA.
+    pub bump: u8,
+}
+
+impl ExecutionPool {
+    // 4 + 32 + (4 + 32 * MAX_POOL_MEMBERS) + (4 + 32 * MAX_POOL_MEMBERS) + 32 + 1 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 4 + 32 + (4 + 32 * MAX_POOL_MEMBERS) + (4 + 32 * MAX_POOL_MEMBERS) + 32 + 1;
+}
+
+#[account]
+pub struct PoolAllocation {
+    pub pool: Pubkey,
+    pub epoch: u64,
+    /// Membership root when the pool was registered for the epoch; claims prove against it.
+    pub membership_root: [u8; 32],
+    pub member_count: u8,
+    /// Number of slots claimed so far, at most one per member.
+    pub claimed_count: u8,
+    /// Bump seed of the pool allocation PDA.
+    pub bump: u8,
+}
+
+impl PoolAllocation {
+    // 32 + 8 + 32 + 1 + 1 + 1 = 75 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 32 + 8 + 32 + 1 + 1 + 1;
+}
+
+#[account]
+pub struct SlotClaim {
+    pub pool: Pubkey,
+    pub epoch: u64,
+    /// Nullifier of the claiming member for this epoch.
+    pub nullifier: [u8; 32],
+    /// Signer that submitted the claim, not necessarily a member's wallet.
+    pub claimant: Pubkey,
+    /// Share of the pool's capacity granted to the claim, in transactions per slot.
+    pub units: u64,
+    /// Time window in which the slot is valid.
+    pub window_start: i64,
+    pub window_end: i64,
+    /// Bump seed of the slot claim PDA.
+    pub bump: u8,
+}
+
+impl SlotClaim {
+    // 32 + 8 + 32 + 32 + 8 + 8 + 8 + 1 = 129 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 32 + 8 + 32 + 32 + 8 + 8 + 8 + 1;
+}
+
+#[account]
+pub struct RewardSchedule {
+    /// Length of an epoch, in seconds.
+    pub epoch_duration: i64,
+    /// Emission of the first epoch.
+    pub initial_emission: u64,
+    /// How the emission decreases from one epoch to the next.
+    pub decay: EmissionDecay,
+    /// Share of each epoch's emission paid to LPs, in basis points. The rest goes to traders.
+    pub lp_share_bps: u16,
+    pub funding: RewardFunding,
+    /// Number of epochs released so far.
+    pub epoch: u64,
+    /// Scheduled start of the next epoch, the earliest time `advance_epoch` can release it.
+    pub next_epoch_start: i64,
+    /// Total rewards released over all epochs.
+    pub total_released: u64,
+    /// Emission left over from rounding the last epoch's reward rates, added to the next budget.
+    pub carried_emission: u64,
+    /// Bump seed of the reward schedule PDA.
+    pub bump: u8,
+}
+
+impl RewardSchedule {
+    // 8 + 8 + (1 + 8) + 2 + 1 + 8 + 8 + 8 + 8 + 1 = 61 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 8 + 8 + (1 + 8) + 2 + 1 + 8 + 8 + 8 + 8 + 1;
+
+    /// Emission of the given epoch.
+    fn emission(&self, epoch: u64) -> u64 {
+        match self.decay {
+            EmissionDecay::Constant => self.initial_emission,
+            EmissionDecay::Linear { decrement } => self
+                .initial_emission
+                .saturating_sub(decrement.saturating_mul(epoch)),
+            EmissionDecay::Halving { interval } => {
+                let halvings = epoch / interval;
+                if halvings >= u64::BITS as u64 {
+                    0
+                } else {
+                    self.initial_emission >> halvings
+                }
+            }
+        }
+    }
+}
+
+/// Parameters of `initialize_reward_schedule`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RewardScheduleParams {
+    pub epoch_duration: i64,
+    pub initial_emission: u64,
+    pub decay: EmissionDecay,
+    pub lp_share_bps: u16,
+    pub funding: RewardFunding,
+}
+
+impl RewardScheduleParams {
+    fn validate(&self) -> Result<()> {
+        let valid_decay = match self.decay {
+            EmissionDecay::Halving { interval } => interval > 0,
+            _ => true,
+        };
+        if !(MIN_EPOCH_DURATION..=MAX_EPOCH_DURATION).contains(&self.epoch_duration)
+            || self.lp_share_bps as u64 > BPS_DENOMINATOR
+            || !valid_decay
+        {
+            return Err(ErrorCode::InvalidConfigValue.into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum EmissionDecay {
+    /// Every epoch emits `initial_emission`.
+    Constant,
+    /// Each epoch emits `decrement` less than the previous one, down to zero.
+    Linear { decrement: u64 },
+    /// The emission halves every `interval` epochs.
+    Halving { interval: u64 },
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RewardFunding {
+    /// `advance_epoch` mints each epoch's emission into the rewards vault.
+    /// The rewards vault authority must be the reward mint's mint authority.
+    Mint,
+    /// The admin funds the rewards vault by transferring reward tokens into it.
+    Vault,
+}
+
+#[account]
+pub struct Treasury {
+    /// Share of incoming funds credited to the insurance reserve, in basis points.
+    pub insurance_share_bps: u16,
+    /// Insurance reserve held in the treasury vault.
+    pub insurance_balance: u64,
+    /// Protocol revenue held in the treasury vault.
+    pub revenue_balance: u64,
+    pub vault_bump: u8,
+    pub authority_bump: u8,
+    /// Bump seed of the treasury PDA.
+    pub bump: u8,
+}
+
+impl Treasury {
+    // 2 + 8 + 8 + 1 + 1 + 1 = 21 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 2 + 8 + 8 + 1 + 1 + 1;
+
+    /// Credits incoming funds to the insurance reserve and protocol revenue.
+    fn deposit(&mut self, amount: u64) -> Result<()> {
+        let insurance = amount
+            .checked_mul(self.insurance_share_bps as u64)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(BPS_DENOMINATOR)
+            .ok_or(ErrorCode::DivisionByZero)?;
+        let revenue = amount.checked_sub(insurance).ok_or(ErrorCode::Underflow)?;
+        self.insurance_balance = self
+            .insurance_balance
+            .checked_add(insurance)
+            .ok_or(ErrorCode::Overflow)?;
+        self.revenue_balance = self
+            .revenue_balance
+            .checked_add(revenue)
+            .ok_or(ErrorCode::Overflow)?;
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TreasuryBucket {
+    Insurance,
+    Revenue,
+}
+
+#[account]
+pub struct TraderAccount {
+    pub trader: Pubkey,
+    /// Global state the trader is registered under.
+    pub global_state: Pubkey,
+    /// In a true confidential system, this value is stored encrypted.
+    pub staked_amount: u64,
+    pub is_verified: bool,
+    pub proof_expiry: i64,
+    pub last_proof_update: i64,
+    pub zk_proof_hash: [u8; 32],
+    pub invalid_proof_attempts: u8,
+    /// Commitment for encrypted order matching.
+    pub commitment: [u8; 32],
+    pub last_stake_timestamp: i64,
+    /// Adaptive rewards: higher multiplier for faster execution.
+    pub speed_multiplier: u64,
+    /// Trade volume credited from venue-signed fill receipts, added to bandwidth priority.
+    pub trade_volume: u64,
+    /// Stake encrypted under the stake vault's ElGamal key (confidential mode).
+    pub encrypted_stake: [u8; 64],
+    /// Settled rewards, paid out by `claim_trader_rewards`.
+    pub reward_balance: u64,
+    /// Rewards already accounted for at the current `trader_reward_per_share`, scaled by
+    /// `REWARD_PER_SHARE_PRECISION`.
+    pub reward_debt: u128,
+    /// Bump seed of the trader PDA.
+    pub bump: u8,
+}
+
+impl TraderAccount {
+    // 32 + 32 + 8 + 1 + 8 + 8 + 32 + 1 + 32 + 8 + 8 + 8 + 64 + 8 + 16 + 1 = 267 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 32 + 32 + 8 + 1 + 8 + 8 + 32 + 1 + 32 + 8 + 8 + 8 + 64 + 8 + 16 + 1;
+
+    /// Credits rewards accrued on the stake since the last settlement to `reward_balance`.
+    /// Rewards are only credited while the trader is verified; otherwise they are forfeited
+    /// back to the undistributed emission, which funds later epochs.
+    fn settle_rewards(&mut self, global_state: &mut GlobalState, now: i64) -> Result<()> {
+        let pending = settle_reward_index(
+            self.staked_amount,
+            global_state.trader_reward_per_share,
+            &mut self.reward_debt,
+        )?;
+        if self.is_verified && now <= self.proof_expiry {
+            self.reward_balance = self
+                .reward_balance
+                .checked_add(pending)
+                .ok_or(ErrorCode::Overflow)?;
+        } else {
+            global_state.reduce_rewards_owed(pending)?;
+        }
+        Ok(())
+    }
+
+    /// Marks the rewards of the current stake as accounted for, after it changed.
+    fn reset_reward_debt(&mut self, trader_reward_per_share: u128) -> Result<()> {
+        self.reward_debt = (self.staked_amount as u128)
+            .checked_mul(trader_reward_per_share)
+            .ok_or(ErrorCode::Overflow)?;
+        Ok(())
+    }
+
+    /// Effective bandwidth priority: the stake decayed linearly over `decay_period` since the
+    /// last proof, scaled by the speed multiplier, plus the confidential trade volume.
+    fn bandwidth_priority(&self, decay_period: i64, now: i64) -> Result<u64> {
+        let elapsed = now - self.last_proof_update;
+        let decay_factor = if elapsed < decay_period {
+            decay_period - elapsed
+        } else {
+            0
+        };
+        let base_priority = self
+            .staked_amount
+            .checked_mul(decay_factor as u64)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(decay_period as u64)
+            .ok_or(ErrorCode::Underflow)?;
+        // Incorporate the speed multiplier and confidential trade volume.
+        let effective_priority = base_priority
+            .checked_mul(self.speed_multiplier)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_add(self.trade_volume)
+            .ok_or(ErrorCode::Overflow)?;
+        Ok(effective_priority)
+    }
+}
+
+#[account]
+pub struct LiquidityAccount {
+    pub lp: Pubkey,
+    /// Confidential liquidity provided (encrypted in production), including the priority pool bonus.
+    pub liquidity_provided: u64,
+    /// Tokens deposited into the liquidity vault and not yet withdrawn.
+    pub deposited_amount: u64,
+    pub is_priority_pool: bool,
+    pub reward_balance: u64,
+    /// Rewards already accounted for at the current `lp_reward_per_share`, scaled by
+    /// `REWARD_PER_SHARE_PRECISION`.
+    pub reward_debt: u128,
+    /// Timestamp when liquidity was last locked, by registration or a deposit.
+    pub lock_timestamp: i64,
+    /// Trade volume credited from venue-signed fill receipts, for market-making incentives.
+    pub trade_volume: u64,
+    /// Liquidity encrypted under the liquidity vault's ElGamal key (confidential mode).
+    pub encrypted_liquidity: [u8; 64],
+    /// Bump seed of the LP PDA.
+    pub bump: u8,
+}
+
+impl LiquidityAccount {
+    // 32 + 8 + 8 + 1 + 8 + 16 + 8 + 8 + 64 + 1 = 154 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 32 + 8 + 8 + 1 + 8 + 16 + 8 + 8 + 64 + 1;
+
+    /// Credits rewards accrued on the liquidity since the last settlement to `reward_balance`.
+    fn settle_rewards(&mut self, lp_reward_per_share: u128) -> Result<()> {
+        let pending = settle_reward_index(
+            self.liquidity_provided,
+            lp_reward_per_share,
+            &mut self.reward_debt,
+        )?;
+        self.reward_balance = self
+            .reward_balance
+            .checked_add(pending)
+            .ok_or(ErrorCode::Overflow)?;
+        Ok(())
+    }
+
+    /// Marks the rewards of the current liquidity as accounted for, after it changed.
+    fn reset_reward_debt(&mut self, lp_reward_per_share: u128) -> Result<()> {
+        self.reward_debt = (self.liquidity_provided as u128)
+            .checked_mul(lp_reward_per_share)
+            .ok_or(ErrorCode::Overflow)?;
+        Ok(())
+    }
+}
+
+/// Groth16 verifying key points passed to `add_verifying_key`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VerifyingKeyParams {
+    pub alpha_g1: [u8; 64],
+    pub beta_g2: [u8; 128],
+    pub gamma_g2: [u8; 128],
+    pub delta_g2: [u8; 128],
+    pub ic: Vec<[u8; 64]>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyingKeyStatus {
+    Pending,
+    Active,
+    Retired,
+}
+
+#[account]
+pub struct VerifyingKey {
+    pub circuit_id: u32,
+    pub version: u32,
+    pub status: VerifyingKeyStatus,
+    /// Groth16 verifying key points, big-endian alt_bn128 encoding.
+    pub alpha_g1: [u8; 64],
+    pub beta_g2: [u8; 128],
+    pub gamma_g2: [u8; 128],
+    pub delta_g2: [u8; 128],
+    /// One G1 point per public input, plus the constant term.
+    pub ic: Vec<[u8; 64]>,
+    pub bump: u8,
+}
+
+impl VerifyingKey {
+    // 4 + 4 + 1 + 64 + 128 * 3 + (4 + 64 * (MAX_PUBLIC_INPUTS + 1)) + 1 bytes (plus 8-byte discriminator)
+    const SIZE: usize = 4 + 4 + 1 + 64 + 128 * 3 + (4 + 64 * (MAX_PUBLIC_INPUTS + 1)) + 1;
+}
+
+//
+// Events
+//
+
+#[event]
+pub struct AdminProposed {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+}
+
+#[event]
+pub struct AdminChanged {
+    pub old_admin: Pubkey,
+    pub new_admin: Pubkey,
+}
+
+#[event]
+pub struct PauseUpdated {
+    pub old: u8,
+    pub new: u8,
+}
+
+#[event]
+pub struct InvalidProofRecorded {
+    pub trader: Pubkey,
+    pub invalid_proof_attempts: u8,
+}
+
+#[event]
+pub struct TraderSlashed {
+    pub trader: Pubkey,
+    /// Stake moved to the treasury.
+    pub slashed_amount: u64,
+}
+
+#[event]
+pub struct TreasurySplitUpdated {
+    pub old: u16,
+    pub new: u16,
+}
+
+#[event]
+pub struct TreasuryWithdrawn {
+    pub bucket: TreasuryBucket,
+    pub amount: u64,
+    pub destination: Pubkey,
+}
+
+#[event]
+pub struct EpochAdvanced {
+    /// Index of the released epoch.
+    pub epoch: u64,
+    pub lp_reward_rate: u64,
+    pub trader_reward_rate: u64,
+    pub released: u64,
+}
+
+#[event]
+pub struct ExecutionSlotClaimed {
+    pub pool: Pubkey,
+    pub epoch: u64,
+    pub nullifier: [u8; 32],
+    pub claimant: Pubkey,
+    pub units: u64,
+}
+
+#[event]
+pub struct CommitmentAppended {
+    pub leaf_index: u32,
+    pub commitment: [u8; 32],
+    pub root: [u8; 32],
+}
+
+#[event]
+pub struct TradeVolumeRecorded {
+    pub venue: Pubkey,
+    pub receipt_id: [u8; 32],
+    pub participant: Pubkey,
+    pub role: FillRole,
+    pub volume: u64,
+}
+
+#[event]
+pub struct OrderPlaced {
+    pub market: Pubkey,
+    pub trader: Pubkey,
+    pub side: OrderSide,
+    pub price: u64,
+    pub size: u64,
+    pub sequence: u64,
+}
+
+#[event]
+pub struct OrderCancelled {
+    pub market: Pubkey,
+    pub trader: Pubkey,
+    pub side: OrderSide,
+    pub sequence: u64,
+}
+
+#[event]
+pub struct AuctionCleared {
+    pub market: Pubkey,
+    pub batch: u64,
+    pub clearing_price: u64,
+    pub volume: u64,
+    pub fill_count: u32,
+}
+
+#[event]
+pub struct ConfigUpdated {
+    pub old: ProtocolConfigParams,
+    pub new: ProtocolConfigParams,
+}
+
+//
+// Instruction Contexts
+//
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(init, payer = admin, space = 8 + GlobalState::SIZE)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ProtocolConfig::SIZE,
+        seeds = [PROTOCOL_CONFIG_SEED, global_state.key().as_ref()],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateAdmin<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    pub new_admin: Signer<'info>,
+    #[account(
+        mut,
+        constraint = global_state.pending_admin == Some(new_admin.key()) @ ErrorCode::Unauthorized
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [PROTOCOL_CONFIG_SEED, global_state.key().as_ref()],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeVaults<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut, has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: PDA authority for the stake vault.
+    #[account(seeds = [STAKE_AUTHORITY_SEED, global_state.key().as_ref()], bump)]
+    pub stake_authority: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [STAKE_VAULT_SEED, global_state.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = stake_authority,
+        token::token_program = token_program
+    )]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA authority for the liquidity vault.
+    #[account(seeds = [LIQUIDITY_AUTHORITY_SEED, global_state.key().as_ref()], bump)]
+    pub liquidity_authority: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [LIQUIDITY_VAULT_SEED, global_state.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = liquidity_authority,
+        token::token_program = token_program
+    )]
+    pub liquidity_vault: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRewardSchedule<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(address = global_state.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: PDA authority for the rewards vault.
+    #[account(
+        seeds = [REWARDS_AUTHORITY_SEED, global_state.key().as_ref()],
+        bump = global_state.rewards_authority_bump
+    )]
+    pub rewards_authority: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + RewardSchedule::SIZE,
+        seeds = [REWARD_SCHEDULE_SEED, global_state.key().as_ref()],
+        bump
+    )]
+    pub reward_schedule: Account<'info, RewardSchedule>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdvanceEpoch<'info> {
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [REWARD_SCHEDULE_SEED, global_state.key().as_ref()],
+        bump = reward_schedule.bump
+    )]
+    pub reward_schedule: Account<'info, RewardSchedule>,
+    #[account(mut, address = global_state.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: PDA authority for the rewards vault, mints rewards in `Mint` funding mode.
+    #[account(
+        seeds = [REWARDS_AUTHORITY_SEED, global_state.key().as_ref()],
+        bump = global_state.rewards_authority_bump
+    )]
+    pub rewards_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [REWARDS_VAULT_SEED, global_state.key().as_ref()],
+        bump = global_state.rewards_vault_bump
+    )]
+    pub rewards_vault: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRewardsVault<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut, has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: PDA authority for the rewards vault.
+    #[account(seeds = [REWARDS_AUTHORITY_SEED, global_state.key().as_ref()], bump)]
+    pub rewards_authority: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [REWARDS_VAULT_SEED, global_state.key().as_ref()],
+        bump,
+        token::mint = reward_mint,
+        token::authority = rewards_authority,
+        token::token_program = token_program
+    )]
+    pub rewards_vault: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_kind: VaultKind)]
+pub struct ConfigureConfidentialVault<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(address = global_state.token_mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: PDA authority of the vault.
+    #[account(
+        seeds = [vault_kind.authority_seed(), global_state.key().as_ref()],
+        bump = global_state.vault_authority_bump(vault_kind)
+    )]
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [vault_kind.vault_seed(), global_state.key().as_ref()],
+        bump = global_state.vault_bump(vault_kind)
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Pubkey validity proof context state account, verified by the token program.
+    pub pubkey_validity_proof: AccountInfo<'info>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault_kind: VaultKind)]
+pub struct ApplyVaultPendingBalance<'info> {
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    /// CHECK: PDA authority of the vault.
+    #[account(
+        seeds = [vault_kind.authority_seed(), global_state.key().as_ref()],
+        bump = global_state.vault_authority_bump(vault_kind)
+    )]
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [vault_kind.vault_seed(), global_state.key().as_ref()],
+        bump = global_state.vault_bump(vault_kind)
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeTreasury<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(address = global_state.token_mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Treasury::SIZE,
+        seeds = [TREASURY_SEED, global_state.key().as_ref()],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    /// CHECK: PDA authority for the treasury vault.
+    #[account(seeds = [TREASURY_AUTHORITY_SEED, global_state.key().as_ref()], bump)]
+    pub treasury_authority: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [TREASURY_VAULT_SEED, global_state.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = treasury_authority,
+        token::token_program = token_program
+    )]
+    pub treasury_vault: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct SetTreasurySplit<'info> {
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, global_state.key().as_ref()],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, global_state.key().as_ref()],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    /// CHECK: PDA authority for the treasury vault.
+    #[account(
+        seeds = [TREASURY_AUTHORITY_SEED, global_state.key().as_ref()],
+        bump = treasury.authority_bump
+    )]
+    pub treasury_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [TREASURY_VAULT_SEED, global_state.key().as_ref()],
+        bump = treasury.vault_bump
+    )]
+    pub treasury_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = global_state.token_mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(circuit_id: u32, version: u32)]
+pub struct AddVerifyingKey<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + VerifyingKey::SIZE,
+        seeds = [
+            VERIFYING_KEY_SEED,
+            global_state.key().as_ref(),
+            &circuit_id.to_le_bytes(),
+            &version.to_le_bytes()
+        ],
+        bump
+    )]
+    pub verifying_key: Account<'info, VerifyingKey>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateVerifyingKey<'info> {
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [
+            VERIFYING_KEY_SEED,
+            global_state.key().as_ref(),
+            &verifying_key.circuit_id.to_le_bytes(),
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Account<'info, VerifyingKey>,
+}
+
+#[derive(Accounts)]
+#[instruction(oracle: Pubkey)]
+pub struct AddLatencyOracle<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + LatencyOracle::SIZE,
+        seeds = [LATENCY_ORACLE_SEED, global_state.key().as_ref(), oracle.as_ref()],
+        bump
+    )]
+    pub latency_oracle: Account<'info, LatencyOracle>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveLatencyOracle<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [LATENCY_ORACLE_SEED, global_state.key().as_ref(), latency_oracle.oracle.as_ref()],
+        bump = latency_oracle.bump
+    )]
+    pub latency_oracle: Account<'info, LatencyOracle>,
+}
+
+#[derive(Accounts)]
+pub struct IssueLatencyChallenge<'info> {
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    #[account(
+        seeds = [TRADER_SEED, global_state.key().as_ref(), trader.key().as_ref()],
+        bump = trader_account.bump,
+        has_one = trader
+    )]
+    pub trader_account: Account<'info, TraderAccount>,
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        init,
+        payer = trader,
+        space = 8 + LatencyChallenge::SIZE,
+        seeds = [LATENCY_CHALLENGE_SEED, global_state.key().as_ref(), trader.key().as_ref()],
+        bump
+    )]
+    pub latency_challenge: Account<'info, LatencyChallenge>,
+    /// CHECK: SlotHashes sysvar, read raw for the most recent slot hash.
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RespondLatencyChallenge<'info> {
+    pub trader: Signer<'info>,
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [LATENCY_CHALLENGE_SEED, global_state.key().as_ref(), trader.key().as_ref()],
+        bump = latency_challenge.bump,
+        has_one = trader
+    )]
+    pub latency_challenge: Account<'info, LatencyChallenge>,
+    /// CHECK: Instructions sysvar, read for the responder's Ed25519 signature.
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(venue: Pubkey)]
+pub struct AddTradeVenue<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + TradeVenue::SIZE,
+        seeds = [TRADE_VENUE_SEED, global_state.key().as_ref(), venue.as_ref()],
+        bump
+    )]
+    pub trade_venue: Account<'info, TradeVenue>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveTradeVenue<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [TRADE_VENUE_SEED, global_state.key().as_ref(), trade_venue.venue.as_ref()],
+        bump = trade_venue.bump
+    )]
+    pub trade_venue: Account<'info, TradeVenue>,
+}
+
+#[derive(Accounts)]
+#[instruction(receipt: FillReceipt)]
+pub struct RecordFillReceipt<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        seeds = [TRADE_VENUE_SEED, global_state.key().as_ref(), trade_venue.venue.as_ref()],
+        bump = trade_venue.bump
+    )]
+    pub trade_venue: Account<'info, TradeVenue>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + FillRecord::SIZE,
+        seeds = [
+            FILL_RECORD_SEED,
+            global_state.key().as_ref(),
+            trade_venue.venue.as_ref(),
+            receipt.receipt_id.as_ref()
+        ],
+        bump
+    )]
+    pub fill_record: Account<'info, FillRecord>,
+    /// Required for `FillRole::Trader` receipts.
+    #[account(
+        mut,
+        seeds = [TRADER_SEED, global_state.key().as_ref(), receipt.participant.as_ref()],
+        bump = trader_account.bump
+    )]
+    pub trader_account: Option<Account<'info, TraderAccount>>,
+    /// Required for `FillRole::LiquidityProvider` receipts.
+    #[account(
+        mut,
+        seeds = [LP_SEED, global_state.key().as_ref(), receipt.participant.as_ref()],
+        bump = lp_account.bump
+    )]
+    pub lp_account: Option<Account<'info, LiquidityAccount>>,
+    /// CHECK: Instructions sysvar, read for the venue's Ed25519 signature.
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market: Pubkey)]
+pub struct InitializeOrderBook<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        seeds = [PROTOCOL_CONFIG_SEED, global_state.key().as_ref()],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + OrderBook::SIZE,
+        seeds = [ORDER_BOOK_SEED, global_state.key().as_ref(), market.as_ref()],
+        bump
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(batch: u64)]
+pub struct ClearAuction<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [ORDER_BOOK_SEED, global_state.key().as_ref(), order_book.load()?.market.as_ref()],
+        bump = order_book.load()?.bump
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + AuctionClearing::SIZE,
+        seeds = [AUCTION_CLEARING_SEED, order_book.key().as_ref(), &batch.to_le_bytes()],
+        bump
+    )]
+    pub auction_clearing: AccountLoader<'info, AuctionClearing>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    pub trader: Signer<'info>,
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [ORDER_BOOK_SEED, global_state.key().as_ref(), order_book.load()?.market.as_ref()],
+        bump = order_book.load()?.bump
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeCommitmentTree<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + CommitmentTree::SIZE,
+        seeds = [COMMITMENT_TREE_SEED, global_state.key().as_ref()],
+        bump
+    )]
+    pub commitment_tree: Box<Account<'info, CommitmentTree>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterTrader<'info> {
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    #[account(
+        init,
+        payer = trader,
+        space = 8 + TraderAccount::SIZE,
+        seeds = [TRADER_SEED, global_state.key().as_ref(), trader.key().as_ref()],
+        bump
+    )]
+    pub trader_account: Account<'info, TraderAccount>,
+    pub global_state: Account<'info, GlobalState>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterLp<'info> {
+    #[account(mut)]
+    pub lp: Signer<'info>,
+    #[account(
+        init,
+        payer = lp,
+        space = 8 + LiquidityAccount::SIZE,
+        seeds = [LP_SEED, global_state.key().as_ref(), lp.key().as_ref()],
+        bump
+    )]
+    pub lp_account: Account<'info, LiquidityAccount>,
+    pub global_state: Account<'info, GlobalState>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPriorityPool<'info> {
+    pub admin: Signer<'info>,
+    #[account(has_one = admin @ ErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [LP_SEED, global_state.key().as_ref(), lp_account.lp.as_ref()],
+        bump = lp_account.bump
+    )]
+    pub lp_account: Account<'info, LiquidityAccount>,
+}
+
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    #[account(mut)]
+    pub trader_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [STAKE_VAULT_SEED, global_state.key().as_ref()],
+        bump = global_state.stake_vault_bump
+    )]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [TRADER_SEED, global_state.key().as_ref(), trader.key().as_ref()],
+        bump = trader_account.bump,
+        has_one = trader
+    )]
+    pub trader_account: Account<'info, TraderAccount>,
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(address = global_state.token_mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: Equality proof context state account, verified in confidential mode.
+    pub equality_proof: Option<AccountInfo<'info>>,
+    /// CHECK: Ciphertext validity proof context state account, verified in confidential mode.
+    pub ciphertext_validity_proof: Option<AccountInfo<'info>>,
+    /// CHECK: Range proof context state account, verified in confidential mode.
+    pub range_proof: Option<AccountInfo<'info>>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
+#[instruction(zk_proof: Vec<u8>, commitment: [u8; 32], nullifier: [u8; 32], latency: LatencySource, vk_version: u32)]
 pub struct VerifyPriority<'info> {
     #[account(mut)]
     pub trader: Signer<'info>,
-    #[account(mut, has_one = trader)]
+    #[account(
+        mut,
+        seeds = [TRADER_SEED, global_state.key().as_ref(), trader.key().as_ref()],
+        bump = trader_account.bump,
+        has_one = trader
+    )]
     pub trader_account: Account<'info, TraderAccount>,
     #[account(mut)]
     pub global_state: Account<'info, GlobalState>,
+    #[account(
+        seeds = [PROTOCOL_CONFIG_SEED, global_state.key().as_ref()],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(
+        seeds = [
+            VERIFYING_KEY_SEED,
+            global_state.key().as_ref(),
+            &PRIORITY_CIRCUIT_ID.to_le_bytes(),
+            &vk_version.to_le_bytes()
+        ],
+        bump = verifying_key.bump,
+        constraint = verifying_key.status == VerifyingKeyStatus::Active @ ErrorCode::VerifyingKeyNotActive
+    )]
+    pub verifying_key: Account<'info, VerifyingKey>,
+    #[account(
+        mut,
+        seeds = [STAKE_VAULT_SEED, global_state.key().as_ref()],
+        bump = global_state.stake_vault_bump
+    )]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA authority for the stake vault, signs fee burns.
+    #[account(
+        seeds = [STAKE_AUTHORITY_SEED, global_state.key().as_ref()],
+        bump = global_state.stake_authority_bump
+    )]
+    pub stake_authority: AccountInfo<'info>,
+    #[account(mut, address = global_state.token_mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// CHECK: Nullifier PDA of the proof, created by `record_nullifier` once the proof is accepted.
+    #[account(
+        mut,
+        seeds = [
+            NULLIFIER_SEED,
+            global_state.key().as_ref(),
+            &PRIORITY_CIRCUIT_ID.to_le_bytes(),
+            nullifier.as_ref()
+        ],
+        bump
+    )]
+    pub proof_nullifier: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [COMMITMENT_TREE_SEED, global_state.key().as_ref()],
+        bump = commitment_tree.bump
+    )]
+    pub commitment_tree: Box<Account<'info, CommitmentTree>>,
+    #[account(
+        seeds = [LATENCY_ORACLE_SEED, global_state.key().as_ref(), latency_oracle.oracle.as_ref()],
+        bump = latency_oracle.bump
+    )]
+    pub latency_oracle: Option<Account<'info, LatencyOracle>>,
+    #[account(
+        mut,
+        close = trader,
+        seeds = [LATENCY_CHALLENGE_SEED, global_state.key().as_ref(), trader.key().as_ref()],
+        bump = latency_challenge.bump,
+        has_one = trader
+    )]
+    pub latency_challenge: Option<Account<'info, LatencyChallenge>>,
+    /// CHECK: Instructions sysvar, read to find the oracle's Ed25519 signature.
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
+#[instruction(amount: TransferAmount, zk_proof: Vec<u8>, commitment: [u8; 32], nullifier: [u8; 32], latency: LatencySource, vk_version: u32)]
 pub struct BatchStakeAndVerify<'info> {
     #[account(mut)]
     pub trader: Signer<'info>,
     #[account(mut)]
-    pub trader_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub stake_vault: Account<'info, TokenAccount>,
-    #[account(mut, has_one = trader)]
+    pub trader_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [STAKE_VAULT_SEED, global_state.key().as_ref()],
+        bump = global_state.stake_vault_bump
+    )]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA authority for the stake vault, signs fee burns.
+    #[account(
+        seeds = [STAKE_AUTHORITY_SEED, global_state.key().as_ref()],
+        bump = global_state.stake_authority_bump
+    )]
+    pub stake_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [TRADER_SEED, global_state.key().as_ref(), trader.key().as_ref()],
+        bump = trader_account.bump,
+        has_one = trader
+    )]
     pub trader_account: Account<'info, TraderAccount>,
     #[account(mut)]
     pub global_state: Account<'info, GlobalState>,
-    pub token_program: Program<'info, Token>,
+    #[account(
+        seeds = [PROTOCOL_CONFIG_SEED, global_state.key().as_ref()],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(
+        seeds = [
+            VERIFYING_KEY_SEED,
+            global_state.key().as_ref(),
+            &PRIORITY_CIRCUIT_ID.to_le_bytes(),
+            &vk_version.to_le_bytes()
+        ],
+        bump = verifying_key.bump,
+        constraint = verifying_key.status == VerifyingKeyStatus::Active @ ErrorCode::VerifyingKeyNotActive
+    )]
+    pub verifying_key: Account<'info, VerifyingKey>,
+    #[account(mut, address = global_state.token_mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: Equality proof context state account, verified in confidential mode.
+    pub equality_proof: Option<AccountInfo<'info>>,
+    /// CHECK: Ciphertext validity proof context state account, verified in confidential mode.
+    pub ciphertext_validity_proof: Option<AccountInfo<'info>>,
+    /// CHECK: Range proof context state account, verified in confidential mode.
+    pub range_proof: Option<AccountInfo<'info>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// CHECK: Nullifier PDA of the proof, created by `record_nullifier` once the proof is accepted.
+    #[account(
+        mut,
+        seeds = [
+            NULLIFIER_SEED,
+            global_state.key().as_ref(),
+            &PRIORITY_CIRCUIT_ID.to_le_bytes(),
+            nullifier.as_ref()
+        ],
+        bump
+    )]
+    pub proof_nullifier: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [COMMITMENT_TREE_SEED, global_state.key().as_ref()],
+        bump = commitment_tree.bump
+    )]
+    pub commitment_tree: Box<Account<'info, CommitmentTree>>,
+    #[account(
+        seeds = [LATENCY_ORACLE_SEED, global_state.key().as_ref(), latency_oracle.oracle.as_ref()],
+        bump = latency_oracle.bump
+    )]
+    pub latency_oracle: Option<Account<'info, LatencyOracle>>,
+    #[account(
+        mut,
+        close = trader,
+        seeds = [LATENCY_CHALLENGE_SEED, global_state.key().as_ref(), trader.key().as_ref()],
+        bump = latency_challenge.bump,
+        has_one = trader
+    )]
+    pub latency_challenge: Option<Account<'info, LatencyChallenge>>,
+    /// CHECK: Instructions sysvar, read to find the oracle's Ed25519 signature.
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct OpenBandwidthEpoch<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        seeds = [PROTOCOL_CONFIG_SEED, global_state.key().as_ref()],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + BandwidthEpoch::SIZE,
+        seeds = [BANDWIDTH_EPOCH_SEED, global_state.key().as_ref(), &epoch.to_le_bytes()],
+        bump
+    )]
+    pub bandwidth_epoch: Account<'info, BandwidthEpoch>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
+#[instruction(epoch: u64)]
 pub struct AllocateBandwidth<'info> {
+    #[account(mut)]
     pub trader: Signer<'info>,
+    #[account(
+        seeds = [TRADER_SEED, global_state.key().as_ref(), trader.key().as_ref()],
+        bump = trader_account.bump,
+        has_one = trader
+    )]
     pub trader_account: Account<'info, TraderAccount>,
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        seeds = [PROTOCOL_CONFIG_SEED, global_state.key().as_ref()],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(
+        mut,
+        seeds = [BANDWIDTH_EPOCH_SEED, global_state.key().as_ref(), &epoch.to_le_bytes()],
+        bump = bandwidth_epoch.bump
+    )]
+    pub bandwidth_epoch: Account<'info, BandwidthEpoch>,
+    #[account(
+        init,
+        payer = trader,
+        space = 8 + BandwidthTicket::SIZE,
+        seeds = [
+            BANDWIDTH_TICKET_SEED,
+            global_state.key().as_ref(),
+            trader.key().as_ref(),
+            &epoch.to_le_bytes()
+        ],
+        bump
+    )]
+    pub bandwidth_ticket: Account<'info, BandwidthTicket>,
+    /// CHECK: Allocation marker of the trader for the epoch, created by the program if unused.
+    #[account(
+        mut,
+        seeds = [
+            TRADER_ALLOCATION_SEED,
+            global_state.key().as_ref(),
+            trader.key().as_ref(),
+            &epoch.to_le_bytes()
+        ],
+        bump
+    )]
+    pub trader_allocation: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct Unstake<'info> {
+pub struct FinalizeBandwidthShare<'info> {
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        seeds = [
+            BANDWIDTH_EPOCH_SEED,
+            global_state.key().as_ref(),
+            &bandwidth_ticket.epoch.to_le_bytes()
+        ],
+        bump = bandwidth_epoch.bump
+    )]
+    pub bandwidth_epoch: Account<'info, BandwidthEpoch>,
+    #[account(
+        mut,
+        seeds = [
+            BANDWIDTH_TICKET_SEED,
+            global_state.key().as_ref(),
+            bandwidth_ticket.trader.as_ref(),
+            &bandwidth_ticket.epoch.to_le_bytes()
+        ],
+        bump = bandwidth_ticket.bump
+    )]
+    pub bandwidth_ticket: Account<'info, BandwidthTicket>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBandwidthTicket<'info> {
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        close = trader,
+        seeds = [
+            BANDWIDTH_TICKET_SEED,
+            global_state.key().as_ref(),
+            trader.key().as_ref(),
+            &bandwidth_ticket.epoch.to_le_bytes()
+        ],
+        bump = bandwidth_ticket.bump,
+        has_one = trader
+    )]
+    pub bandwidth_ticket: Account<'info, BandwidthTicket>,
+}
+
+#[derive(Accounts)]
+#[instruction(pool_id: u32)]
+pub struct CreateExecutionPool<'info> {
     #[account(mut)]
+    pub creator: Signer<'info>,
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + ExecutionPool::SIZE,
+        seeds = [EXECUTION_POOL_SEED, global_state.key().as_ref(), &pool_id.to_le_bytes()],
+        bump
+    )]
+    pub execution_pool: Account<'info, ExecutionPool>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct JoinExecutionPool<'info> {
     pub trader: Signer<'info>,
-    #[account(mut, has_one = trader)]
+    #[account(
+        seeds = [TRADER_SEED, global_state.key().as_ref(), trader.key().as_ref()],
+        bump = trader_account.bump,
+        has_one = trader
+    )]
     pub trader_account: Account<'info, TraderAccount>,
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [
+            EXECUTION_POOL_SEED,
+            global_state.key().as_ref(),
+            &execution_pool.pool_id.to_le_bytes()
+        ],
+        bump = execution_pool.bump
+    )]
+    pub execution_pool: Account<'info, ExecutionPool>,
+}
+
+#[derive(Accounts)]
+pub struct LeaveExecutionPool<'info> {
+    pub trader: Signer<'info>,
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [
+            EXECUTION_POOL_SEED,
+            global_state.key().as_ref(),
+            &execution_pool.pool_id.to_le_bytes()
+        ],
+        bump = execution_pool.bump
+    )]
+    pub execution_pool: Account<'info, ExecutionPool>,
+}
+
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct AllocatePoolBandwidth<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        seeds = [PROTOCOL_CONFIG_SEED, global_state.key().as_ref()],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(
+        seeds = [
+            EXECUTION_POOL_SEED,
+            global_state.key().as_ref(),
+            &execution_pool.pool_id.to_le_bytes()
+        ],
+        bump = execution_pool.bump
+    )]
+    pub execution_pool: Account<'info, ExecutionPool>,
+    #[account(
+        mut,
+        seeds = [BANDWIDTH_EPOCH_SEED, global_state.key().as_ref(), &epoch.to_le_bytes()],
+        bump = bandwidth_epoch.bump
+    )]
+    pub bandwidth_epoch: Account<'info, BandwidthEpoch>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + BandwidthTicket::SIZE,
+        seeds = [
+            BANDWIDTH_TICKET_SEED,
+            global_state.key().as_ref(),
+            execution_pool.key().as_ref(),
+            &epoch.to_le_bytes()
+        ],
+        bump
+    )]
+    pub bandwidth_ticket: Account<'info, BandwidthTicket>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PoolAllocation::SIZE,
+        seeds = [POOL_ALLOCATION_SEED, execution_pool.key().as_ref(), &epoch.to_le_bytes()],
+        bump
+    )]
+    pub pool_allocation: Account<'info, PoolAllocation>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(epoch: u64, nullifier: [u8; 32], zk_proof: Vec<u8>, vk_version: u32)]
+pub struct ClaimExecutionSlot<'info> {
     #[account(mut)]
-    pub trader_token_account: Account<'info, TokenAccount>,
+    pub claimant: Signer<'info>,
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [POOL_ALLOCATION_SEED, pool_allocation.pool.as_ref(), &epoch.to_le_bytes()],
+        bump = pool_allocation.bump
+    )]
+    pub pool_allocation: Account<'info, PoolAllocation>,
+    #[account(
+        seeds = [
+            BANDWIDTH_TICKET_SEED,
+            global_state.key().as_ref(),
+            pool_allocation.pool.as_ref(),
+            &epoch.to_le_bytes()
+        ],
+        bump = bandwidth_ticket.bump
+    )]
+    pub bandwidth_ticket: Account<'info, BandwidthTicket>,
+    #[account(
+        seeds = [
+            VERIFYING_KEY_SEED,
+            global_state.key().as_ref(),
+            &POOL_MEMBERSHIP_CIRCUIT_ID.to_le_bytes(),
+            &vk_version.to_le_bytes()
+        ],
+        bump = verifying_key.bump,
+        constraint = verifying_key.status == VerifyingKeyStatus::Active @ ErrorCode::VerifyingKeyNotActive
+    )]
+    pub verifying_key: Account<'info, VerifyingKey>,
+    #[account(
+        init,
+        payer = claimant,
+        space = 8 + SlotClaim::SIZE,
+        seeds = [
+            SLOT_CLAIM_SEED,
+            pool_allocation.pool.as_ref(),
+            &epoch.to_le_bytes(),
+            nullifier.as_ref()
+        ],
+        bump
+    )]
+    pub slot_claim: Account<'info, SlotClaim>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Unstake<'info> {
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [TRADER_SEED, global_state.key().as_ref(), trader.key().as_ref()],
+        bump = trader_account.bump,
+        has_one = trader
+    )]
+    pub trader_account: Account<'info, TraderAccount>,
     #[account(mut)]
-    pub stake_vault: Account<'info, TokenAccount>,
+    pub trader_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [STAKE_VAULT_SEED, global_state.key().as_ref()],
+        bump = global_state.stake_vault_bump
+    )]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
     /// CHECK: PDA authority for the stake vault.
+    #[account(
+        seeds = [STAKE_AUTHORITY_SEED, global_state.key().as_ref()],
+        bump = global_state.stake_authority_bump
+    )]
     pub stake_authority: AccountInfo<'info>,
     #[account(mut)]
     pub global_state: Account<'info, GlobalState>,
-    pub token_program: Program<'info, Token>,
+    #[account(
+        seeds = [PROTOCOL_CONFIG_SEED, global_state.key().as_ref()],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(address = global_state.token_mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
@@ -557,24 +4825,222 @@ pub struct ProvideLiquidity<'info> {
     #[account(mut)]
     pub lp: Signer<'info>,
     #[account(mut)]
-    pub lp_token_account: Account<'info, TokenAccount>,
+    pub lp_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [LIQUIDITY_VAULT_SEED, global_state.key().as_ref()],
+        bump = global_state.liquidity_vault_bump
+    )]
+    pub liquidity_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [LP_SEED, global_state.key().as_ref(), lp.key().as_ref()],
+        bump = lp_account.bump,
+        has_one = lp
+    )]
+    pub lp_account: Account<'info, LiquidityAccount>,
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        seeds = [PROTOCOL_CONFIG_SEED, global_state.key().as_ref()],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(address = global_state.token_mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: Equality proof context state account, verified in confidential mode.
+    pub equality_proof: Option<AccountInfo<'info>>,
+    /// CHECK: Ciphertext validity proof context state account, verified in confidential mode.
+    pub ciphertext_validity_proof: Option<AccountInfo<'info>>,
+    /// CHECK: Range proof context state account, verified in confidential mode.
+    pub range_proof: Option<AccountInfo<'info>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawLiquidity<'info> {
+    #[account(mut)]
+    pub lp: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [LP_SEED, global_state.key().as_ref(), lp.key().as_ref()],
+        bump = lp_account.bump,
+        has_one = lp
+    )]
+    pub lp_account: Account<'info, LiquidityAccount>,
+    #[account(mut)]
+    pub lp_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [LIQUIDITY_VAULT_SEED, global_state.key().as_ref()],
+        bump = global_state.liquidity_vault_bump
+    )]
+    pub liquidity_vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA authority for the liquidity vault.
+    #[account(
+        seeds = [LIQUIDITY_AUTHORITY_SEED, global_state.key().as_ref()],
+        bump = global_state.liquidity_authority_bump
+    )]
+    pub liquidity_authority: AccountInfo<'info>,
     #[account(mut)]
-    pub liquidity_vault: Account<'info, TokenAccount>,
-    #[account(mut, has_one = lp)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        seeds = [PROTOCOL_CONFIG_SEED, global_state.key().as_ref()],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(address = global_state.token_mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRewards<'info> {
+    pub lp: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [LP_SEED, global_state.key().as_ref(), lp.key().as_ref()],
+        bump = lp_account.bump,
+        has_one = lp
+    )]
     pub lp_account: Account<'info, LiquidityAccount>,
     #[account(mut)]
     pub global_state: Account<'info, GlobalState>,
-    pub token_program: Program<'info, Token>,
+    #[account(
+        seeds = [PROTOCOL_CONFIG_SEED, global_state.key().as_ref()],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    /// CHECK: PDA authority for the rewards vault.
+    #[account(
+        seeds = [REWARDS_AUTHORITY_SEED, global_state.key().as_ref()],
+        bump = global_state.rewards_authority_bump
+    )]
+    pub rewards_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [REWARDS_VAULT_SEED, global_state.key().as_ref()],
+        bump = global_state.rewards_vault_bump
+    )]
+    pub rewards_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub lp_reward_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = global_state.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimTraderRewards<'info> {
+    pub trader: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [TRADER_SEED, global_state.key().as_ref(), trader.key().as_ref()],
+        bump = trader_account.bump,
+        has_one = trader
+    )]
+    pub trader_account: Account<'info, TraderAccount>,
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,
+    /// CHECK: PDA authority for the rewards vault.
+    #[account(
+        seeds = [REWARDS_AUTHORITY_SEED, global_state.key().as_ref()],
+        bump = global_state.rewards_authority_bump
+    )]
+    pub rewards_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [REWARDS_VAULT_SEED, global_state.key().as_ref()],
+        bump = global_state.rewards_vault_bump
+    )]
+    pub rewards_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub trader_reward_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = global_state.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
+#[instruction(order: Order, order_range_proof: Vec<u8>, vk_version: u32)]
 pub struct RevealTrade<'info> {
     #[account(mut)]
     pub trader: Signer<'info>,
-    #[account(mut, has_one = trader)]
+    #[account(
+        mut,
+        seeds = [TRADER_SEED, global_state.key().as_ref(), trader.key().as_ref()],
+        bump = trader_account.bump,
+        has_one = trader
+    )]
     pub trader_account: Account<'info, TraderAccount>,
     #[account(mut)]
     pub global_state: Account<'info, GlobalState>,
+    #[account(
+        seeds = [PROTOCOL_CONFIG_SEED, global_state.key().as_ref()],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(
+        seeds = [
+            VERIFYING_KEY_SEED,
+            global_state.key().as_ref(),
+            &ORDER_RANGE_CIRCUIT_ID.to_le_bytes(),
+            &vk_version.to_le_bytes()
+        ],
+        bump = verifying_key.bump,
+        constraint = verifying_key.status == VerifyingKeyStatus::Active @ ErrorCode::VerifyingKeyNotActive
+    )]
+    pub verifying_key: Account<'info, VerifyingKey>,
+    #[account(
+        mut,
+        seeds = [ORDER_BOOK_SEED, global_state.key().as_ref(), order.market.as_ref()],
+        bump = order_book.load()?.bump
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+}
+
+#[derive(Accounts)]
+pub struct SlashTrader<'info> {
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        seeds = [PROTOCOL_CONFIG_SEED, global_state.key().as_ref()],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(
+        mut,
+        seeds = [TRADER_SEED, global_state.key().as_ref(), trader_account.trader.as_ref()],
+        bump = trader_account.bump
+    )]
+    pub trader_account: Account<'info, TraderAccount>,
+    #[account(
+        mut,
+        seeds = [STAKE_VAULT_SEED, global_state.key().as_ref()],
+        bump = global_state.stake_vault_bump
+    )]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA authority for the stake vault, signs the slashing transfer.
+    #[account(
+        seeds = [STAKE_AUTHORITY_SEED, global_state.key().as_ref()],
+        bump = global_state.stake_authority_bump
+    )]
+    pub stake_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, global_state.key().as_ref()],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    #[account(
+        mut,
+        seeds = [TREASURY_VAULT_SEED, global_state.key().as_ref()],
+        bump = treasury.vault_bump
+    )]
+    pub treasury_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = global_state.token_mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 //
@@ -591,8 +5057,6 @@ pub enum ErrorCode {
     DivisionByZero,
     #[msg("Invalid zero-knowledge proof provided.")]
     InvalidZKProof,
-    #[msg("Invalid reveal: commitment does not match the revealed order or range proof failed.")]
-    InvalidReveal,
     #[msg("Trader is not verified for bandwidth allocation.")]
     TraderNotVerified,
     #[msg("Proof has expired.")]
@@ -605,4 +5069,116 @@ pub enum ErrorCode {
     RevealTooEarly,
     #[msg("Liquidity funds are still locked.")]
     LiquidityLockNotElapsed,
+    #[msg("Signer is not authorized to perform this action.")]
+    Unauthorized,
+    #[msg("Groth16 pairing check failed for the provided proof.")]
+    Groth16VerificationFailed,
+    #[msg("Verifying key does not match the expected circuit.")]
+    InvalidVerifyingKey,
+    #[msg("Verifying key is not active.")]
+    VerifyingKeyNotActive,
+    #[msg("Verifying key status does not allow this operation.")]
+    InvalidVerifyingKeyStatus,
+    #[msg("Token mode is not supported by the mint or token program.")]
+    InvalidTokenMode,
+    #[msg("Transfer amount does not match the token mode.")]
+    TokenModeMismatch,
+    #[msg("Confidential transfer proof context accounts are missing.")]
+    MissingConfidentialProof,
+    #[msg("Confidential transfer proof is invalid.")]
+    InvalidConfidentialProof,
+    #[msg("Protocol parameter is out of bounds.")]
+    InvalidConfigValue,
+    #[msg("Invalid admin public key.")]
+    InvalidAdmin,
+    #[msg("This instruction is paused.")]
+    ProtocolPaused,
+    #[msg("Treasury balance is insufficient for this withdrawal.")]
+    InsufficientTreasuryBalance,
+    #[msg("Rewards vault balance is insufficient for this claim.")]
+    InsufficientRewardsVault,
+    #[msg("Deposited liquidity is insufficient for this withdrawal.")]
+    InsufficientLiquidity,
+    #[msg("The current reward epoch has not ended yet.")]
+    EpochNotElapsed,
+    #[msg(
+        "Mint funding requires the rewards vault authority to be the reward mint's mint authority."
+    )]
+    InvalidRewardFunding,
+    #[msg("Only the next bandwidth epoch can be opened.")]
+    InvalidBandwidthEpoch,
+    #[msg("The bandwidth ticket has not expired yet.")]
+    BandwidthTicketActive,
+    #[msg("Registration for this bandwidth epoch has closed.")]
+    BandwidthRegistrationClosed,
+    #[msg("Registration for this bandwidth epoch is still open.")]
+    BandwidthRegistrationOpen,
+    #[msg("The execution pool has no room for another member.")]
+    ExecutionPoolFull,
+    #[msg("The trader or commitment is already a member of the execution pool.")]
+    AlreadyPoolMember,
+    #[msg("Remaining accounts do not match the execution pool's members.")]
+    InvalidPoolMembers,
+    #[msg("The bandwidth share has not been finalized yet.")]
+    BandwidthShareNotFinalized,
+    #[msg("The execution window for this epoch has closed.")]
+    ExecutionWindowClosed,
+    #[msg("Every member of the execution pool has claimed a slot this epoch.")]
+    PoolSlotsExhausted,
+    #[msg("This proof has already been accepted.")]
+    ProofReplayed,
+    #[msg("The commitment tree is full.")]
+    CommitmentTreeFull,
+    #[msg("No Ed25519 latency attestation precedes this instruction.")]
+    MissingLatencyAttestation,
+    #[msg("The latency attestation is not signed by the oracle or does not match.")]
+    InvalidLatencyAttestation,
+    #[msg("The latency attestation has expired.")]
+    LatencyAttestationExpired,
+    #[msg("The latency challenge has already been answered.")]
+    LatencyChallengeAnswered,
+    #[msg("The latency challenge nonce could not be derived from the slot hashes.")]
+    InvalidChallengeNonce,
+    #[msg("Latency challenges must be answered after the slot they were posted in.")]
+    ChallengeResponseTooEarly,
+    #[msg("No answered latency challenge was provided.")]
+    LatencyChallengeNotAnswered,
+    #[msg("The latency challenge was answered too long ago.")]
+    LatencyChallengeExpired,
+    #[msg("No Ed25519 signature of the fill receipt precedes the instruction.")]
+    MissingFillReceiptSignature,
+    #[msg("The fill receipt is not signed by the venue or does not match.")]
+    InvalidFillReceipt,
+    #[msg("The account to credit the fill receipt's volume to was not provided.")]
+    MissingVolumeAccount,
+    #[msg("The trader has no order commitment to reveal.")]
+    NoOrderCommitment,
+    #[msg("The order encoding version is not supported.")]
+    UnsupportedOrderVersion,
+    #[msg("Orders must have a non-zero price and size.")]
+    InvalidOrder,
+    #[msg("The order book side is full.")]
+    OrderBookFull,
+    #[msg("The batch auction's reveal window has not ended yet.")]
+    AuctionWindowOpen,
+    #[msg("The batch auction has already been cleared.")]
+    AuctionAlreadyCleared,
+    #[msg("No bids and asks cross in the batch auction.")]
+    NoCrossingOrders,
+    #[msg("The trader has not reached the invalid proof threshold for slashing.")]
+    SlashThresholdNotReached,
+    #[msg("This instruction is not supported in confidential mode.")]
+    ConfidentialModeUnsupported,
+    #[msg("The trader is not a member of the execution pool.")]
+    NotPoolMember,
+    #[msg("The trader has already been allocated bandwidth for this epoch.")]
+    BandwidthAlreadyAllocated,
+    #[msg("The trader has no resting order with this sequence number on this side.")]
+    OrderNotFound,
+    #[msg(
+        "The latency challenge answer must follow an Ed25519 instruction signed by its responder."
+    )]
+    MissingChallengeResponse,
+    #[msg("The Ed25519 instruction does not sign the latency challenge for its responder.")]
+    InvalidChallengeResponse,
 }
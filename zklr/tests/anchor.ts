@@ -2,8 +2,155 @@ import * as anchor from "@coral-xyz/anchor";
 import BN from "bn.js";
 import assert from "assert";
 import * as web3 from "@solana/web3.js";
+import { createHash } from "crypto";
 import type { Zklr } from "../target/types/zklr";
 
+const TOKEN_PROGRAM_ID = new web3.PublicKey(
+  "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"
+);
+const MINT_SIZE = 82;
+
+// Creates a plain SPL mint with the provider wallet as mint authority.
+async function createMint(
+  provider: anchor.AnchorProvider,
+  mintKp: web3.Keypair
+): Promise<void> {
+  // InitializeMint2: tag 20, decimals, mint authority, no freeze authority.
+  const initializeMint = new web3.TransactionInstruction({
+    programId: TOKEN_PROGRAM_ID,
+    keys: [{ pubkey: mintKp.publicKey, isSigner: false, isWritable: true }],
+    data: Buffer.concat([
+      Buffer.from([20, 6]),
+      provider.publicKey.toBuffer(),
+      Buffer.from([0]),
+      Buffer.alloc(32),
+    ]),
+  });
+  const tx = new web3.Transaction().add(
+    web3.SystemProgram.createAccount({
+      fromPubkey: provider.publicKey,
+      newAccountPubkey: mintKp.publicKey,
+      lamports:
+        await provider.connection.getMinimumBalanceForRentExemption(MINT_SIZE),
+      space: MINT_SIZE,
+      programId: TOKEN_PROGRAM_ID,
+    }),
+    initializeMint
+  );
+  await provider.sendAndConfirm(tx, [mintKp]);
+}
+
+const TOKEN_2022_PROGRAM_ID = new web3.PublicKey(
+  "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb"
+);
+// Base mint padded to the token account length, the account type byte and
+// the ConfidentialTransferMint extension (type, length and 65 bytes of data).
+const CONFIDENTIAL_MINT_SIZE = 165 + 1 + 4 + 65;
+
+// Creates a Token-2022 mint with the ConfidentialTransfer extension and the
+// provider wallet as mint and confidential transfer authority.
+async function createConfidentialMint(
+  provider: anchor.AnchorProvider,
+  mintKp: web3.Keypair
+): Promise<void> {
+  // ConfidentialTransferExtension (27) InitializeMint (0): authority,
+  // auto-approve new accounts, no auditor.
+  const initializeConfidentialMint = new web3.TransactionInstruction({
+    programId: TOKEN_2022_PROGRAM_ID,
+    keys: [{ pubkey: mintKp.publicKey, isSigner: false, isWritable: true }],
+    data: Buffer.concat([
+      Buffer.from([27, 0]),
+      provider.publicKey.toBuffer(),
+      Buffer.from([1]),
+      Buffer.alloc(32),
+    ]),
+  });
+  const initializeMint = new web3.TransactionInstruction({
+    programId: TOKEN_2022_PROGRAM_ID,
+    keys: [{ pubkey: mintKp.publicKey, isSigner: false, isWritable: true }],
+    data: Buffer.concat([
+      Buffer.from([20, 6]),
+      provider.publicKey.toBuffer(),
+      Buffer.from([0]),
+      Buffer.alloc(32),
+    ]),
+  });
+  const tx = new web3.Transaction().add(
+    web3.SystemProgram.createAccount({
+      fromPubkey: provider.publicKey,
+      newAccountPubkey: mintKp.publicKey,
+      lamports:
+        await provider.connection.getMinimumBalanceForRentExemption(
+          CONFIDENTIAL_MINT_SIZE
+        ),
+      space: CONFIDENTIAL_MINT_SIZE,
+      programId: TOKEN_2022_PROGRAM_ID,
+    }),
+    initializeConfidentialMint,
+    initializeMint
+  );
+  await provider.sendAndConfirm(tx, [mintKp]);
+}
+
+const TOKEN_ACCOUNT_SIZE = 165;
+
+// Creates a token account for `mint` owned by `owner`.
+async function createTokenAccount(
+  provider: anchor.AnchorProvider,
+  mint: web3.PublicKey,
+  owner: web3.PublicKey,
+  programId = TOKEN_PROGRAM_ID
+): Promise<web3.PublicKey> {
+  const accountKp = new web3.Keypair();
+  // InitializeAccount3: tag 18, owner.
+  const initializeAccount = new web3.TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: accountKp.publicKey, isSigner: false, isWritable: true },
+      { pubkey: mint, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.concat([Buffer.from([18]), owner.toBuffer()]),
+  });
+  const tx = new web3.Transaction().add(
+    web3.SystemProgram.createAccount({
+      fromPubkey: provider.publicKey,
+      newAccountPubkey: accountKp.publicKey,
+      lamports:
+        await provider.connection.getMinimumBalanceForRentExemption(
+          TOKEN_ACCOUNT_SIZE
+        ),
+      space: TOKEN_ACCOUNT_SIZE,
+      programId,
+    }),
+    initializeAccount
+  );
+  await provider.sendAndConfirm(tx, [accountKp]);
+  return accountKp.publicKey;
+}
+
+// Mints `amount` tokens to `destination`, signed by the provider wallet.
+async function mintTo(
+  provider: anchor.AnchorProvider,
+  mint: web3.PublicKey,
+  destination: web3.PublicKey,
+  amount: number
+): Promise<void> {
+  // MintTo: tag 7, amount (u64 LE).
+  const instruction = new web3.TransactionInstruction({
+    programId: TOKEN_PROGRAM_ID,
+    keys: [
+      { pubkey: mint, isSigner: false, isWritable: true },
+      { pubkey: destination, isSigner: false, isWritable: true },
+      { pubkey: provider.publicKey, isSigner: true, isWritable: false },
+    ],
+    data: Buffer.concat([
+      Buffer.from([7]),
+      new BN(amount).toArrayLike(Buffer, "le", 8),
+    ]),
+  });
+  await provider.sendAndConfirm(new web3.Transaction().add(instruction));
+}
+
 describe("Test", () => {
   // Configure the client to use the local cluster
   anchor.setProvider(anchor.AnchorProvider.env());
@@ -41,3 +188,2925 @@ describe("Test", () => {
     assert(data.eq(newAccount.data));
   });
 });
+
+describe("trader accounts", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.Zklr as anchor.Program<Zklr>;
+  const admin = program.provider.publicKey;
+  const globalStateKp = new web3.Keypair();
+  const traderKp = new web3.Keypair();
+
+  const [traderAccount, bump] = web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("trader"),
+      globalStateKp.publicKey.toBuffer(),
+      traderKp.publicKey.toBuffer(),
+    ],
+    program.programId
+  );
+  const registerAccounts = {
+    trader: traderKp.publicKey,
+    traderAccount,
+    globalState: globalStateKp.publicKey,
+    systemProgram: web3.SystemProgram.programId,
+  };
+
+  before(async () => {
+    await program.methods
+      .initialize(admin)
+      .accountsPartial({
+        globalState: globalStateKp.publicKey,
+        admin,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([globalStateKp])
+      .rpc();
+
+    const sig = await program.provider.connection.requestAirdrop(
+      traderKp.publicKey,
+      web3.LAMPORTS_PER_SOL
+    );
+    await program.provider.connection.confirmTransaction(sig);
+  });
+
+  it("register_trader creates the trader PDA once", async () => {
+    await program.methods
+      .registerTrader()
+      .accountsPartial(registerAccounts)
+      .signers([traderKp])
+      .rpc();
+
+    const account = await program.account.traderAccount.fetch(traderAccount);
+    assert(account.trader.equals(traderKp.publicKey));
+    assert.strictEqual(account.bump, bump);
+    assert(account.stakedAmount.isZero());
+    assert(!account.isVerified);
+    assert.strictEqual(account.invalidProofAttempts, 0);
+
+    await assert.rejects(
+      program.methods
+        .registerTrader()
+        .accountsPartial(registerAccounts)
+        .signers([traderKp])
+        .rpc(),
+      /already in use/
+    );
+  });
+
+  it("unstake pays stake out of the vault after the lockup", async () => {
+    const provider = program.provider as anchor.AnchorProvider;
+    const mintKp = new web3.Keypair();
+    const [stakeVault] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("stake_vault"), globalStateKp.publicKey.toBuffer()],
+      program.programId
+    );
+    const [protocolConfig] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("protocol_config"), globalStateKp.publicKey.toBuffer()],
+      program.programId
+    );
+    await createMint(provider, mintKp);
+    await program.methods
+      .initializeVaults({ spl: {} })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        mint: mintKp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+    const traderTokenAccount = await createTokenAccount(
+      provider,
+      mintKp.publicKey,
+      traderKp.publicKey
+    );
+    await mintTo(provider, mintKp.publicKey, traderTokenAccount, 1000);
+
+    const stakeAccounts = {
+      trader: traderKp.publicKey,
+      traderTokenAccount,
+      traderAccount,
+      globalState: globalStateKp.publicKey,
+      mint: mintKp.publicKey,
+      equalityProof: null,
+      ciphertextValidityProof: null,
+      rangeProof: null,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    const unstake = (amount: number) =>
+      program.methods
+        .unstake(new BN(amount))
+        .accountsPartial(stakeAccounts)
+        .signers([traderKp])
+        .rpc();
+    const balance = async (account: web3.PublicKey) =>
+      (await provider.connection.getTokenAccountBalance(account)).value
+        .amount;
+
+    await program.methods
+      .stake({ plain: [new BN(1000)] })
+      .accountsPartial(stakeAccounts)
+      .signers([traderKp])
+      .rpc();
+    await assert.rejects(unstake(400), /LockupPeriodNotElapsed/);
+
+    const { bump, ...params } =
+      await program.account.protocolConfig.fetch(protocolConfig);
+    await program.methods
+      .updateConfig({ ...params, lockupPeriod: new BN(0) })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        protocolConfig,
+      })
+      .rpc();
+    await assert.rejects(unstake(1001), /InsufficientStake/);
+
+    // The stake authority PDA signs the transfer out of the vault.
+    await unstake(400);
+    const account = await program.account.traderAccount.fetch(traderAccount);
+    const state = await program.account.globalState.fetch(
+      globalStateKp.publicKey
+    );
+    assert.strictEqual(await balance(stakeVault), "600");
+    assert.strictEqual(await balance(traderTokenAccount), "400");
+    assert(account.stakedAmount.eq(new BN(600)));
+    assert(state.totalStaked.eq(new BN(600)));
+  });
+});
+
+describe("liquidity accounts", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.Zklr as anchor.Program<Zklr>;
+  const admin = program.provider.publicKey;
+  const globalStateKp = new web3.Keypair();
+  const lpKp = new web3.Keypair();
+
+  const [lpAccount] = web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("lp"),
+      globalStateKp.publicKey.toBuffer(),
+      lpKp.publicKey.toBuffer(),
+    ],
+    program.programId
+  );
+
+  before(async () => {
+    await program.methods
+      .initialize(admin)
+      .accountsPartial({
+        globalState: globalStateKp.publicKey,
+        admin,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([globalStateKp])
+      .rpc();
+
+    const sig = await program.provider.connection.requestAirdrop(
+      lpKp.publicKey,
+      web3.LAMPORTS_PER_SOL
+    );
+    await program.provider.connection.confirmTransaction(sig);
+  });
+
+  it("register_lp stamps the lock timestamp", async () => {
+    await program.methods
+      .registerLp()
+      .accountsPartial({
+        lp: lpKp.publicKey,
+        lpAccount,
+        globalState: globalStateKp.publicKey,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([lpKp])
+      .rpc();
+
+    const account = await program.account.liquidityAccount.fetch(lpAccount);
+    assert(account.lp.equals(lpKp.publicKey));
+    assert(!account.isPriorityPool);
+    assert(account.lockTimestamp.gt(new BN(0)));
+  });
+
+  it("set_priority_pool is admin-only", async () => {
+    await assert.rejects(
+      program.methods
+        .setPriorityPool(true)
+        .accountsPartial({
+          admin: lpKp.publicKey,
+          globalState: globalStateKp.publicKey,
+          lpAccount,
+        })
+        .signers([lpKp])
+        .rpc(),
+      /Unauthorized/
+    );
+
+    await program.methods
+      .setPriorityPool(true)
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        lpAccount,
+      })
+      .rpc();
+
+    const account = await program.account.liquidityAccount.fetch(lpAccount);
+    assert(account.isPriorityPool);
+  });
+
+  const mintKp = new web3.Keypair();
+  const [protocolConfig] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("protocol_config"), globalStateKp.publicKey.toBuffer()],
+    program.programId
+  );
+  let lpTokenAccount: web3.PublicKey;
+
+  const setLiquidityLockPeriod = async (period: number) => {
+    const { bump, ...params } =
+      await program.account.protocolConfig.fetch(protocolConfig);
+    await program.methods
+      .updateConfig({ ...params, liquidityLockPeriod: new BN(period) })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        protocolConfig,
+      })
+      .rpc();
+  };
+
+  it("claim_rewards pays out rewards accrued over time", async () => {
+    const provider = program.provider as anchor.AnchorProvider;
+    await createMint(provider, mintKp);
+    await program.methods
+      .initializeVaults({ spl: {} })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        mint: mintKp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+    const [rewardsVault] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("rewards_vault"), globalStateKp.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .initializeRewardsVault()
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        rewardMint: mintKp.publicKey,
+        rewardsVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+
+    // Lift the liquidity lock so that the deposit can be claimed right away.
+    await setLiquidityLockPeriod(0);
+    // One epoch of 600 tokens over 60 seconds, all to LPs: 10 tokens/second.
+    await program.methods
+      .initializeRewardSchedule({
+        epochDuration: new BN(60),
+        initialEmission: new BN(600),
+        decay: { constant: {} },
+        lpShareBps: 10000,
+        funding: { vault: {} },
+      })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        rewardMint: mintKp.publicKey,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+    // The vault must hold the epoch's rewards before they're released.
+    await mintTo(provider, mintKp.publicKey, rewardsVault, 600);
+    await program.methods
+      .advanceEpoch()
+      .accountsPartial({
+        globalState: globalStateKp.publicKey,
+        rewardMint: mintKp.publicKey,
+        rewardsVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    lpTokenAccount = await createTokenAccount(
+      provider,
+      mintKp.publicKey,
+      lpKp.publicKey
+    );
+    await mintTo(provider, mintKp.publicKey, lpTokenAccount, 1000);
+    await program.methods
+      .provideLiquidity({ plain: [new BN(1000)] })
+      .accountsPartial({
+        lp: lpKp.publicKey,
+        lpTokenAccount,
+        lpAccount,
+        globalState: globalStateKp.publicKey,
+        mint: mintKp.publicKey,
+        equalityProof: null,
+        ciphertextValidityProof: null,
+        rangeProof: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([lpKp])
+      .rpc();
+    // The priority pool bonus adds weight to the deposit instead of a payout.
+    let account = await program.account.liquidityAccount.fetch(lpAccount);
+    assert(account.liquidityProvided.eq(new BN(1100)));
+    assert(account.rewardBalance.isZero());
+    await new Promise((resolve) => setTimeout(resolve, 2000));
+
+    const claimAccounts = {
+      lp: lpKp.publicKey,
+      lpAccount,
+      globalState: globalStateKp.publicKey,
+      rewardsVault,
+      lpRewardTokenAccount: lpTokenAccount,
+      rewardMint: mintKp.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    await program.methods
+      .claimRewards()
+      .accountsPartial(claimAccounts)
+      .signers([lpKp])
+      .rpc();
+
+    // The only LP earns the full rate of 10 tokens per second, less rounding.
+    const balance =
+      await provider.connection.getTokenAccountBalance(lpTokenAccount);
+    account = await program.account.liquidityAccount.fetch(lpAccount);
+    assert(Number(balance.value.amount) >= 19);
+    assert(account.rewardBalance.isZero());
+  });
+
+  it("withdraw_liquidity removes deposits with their bonus", async () => {
+    const withdraw = (amount: number) =>
+      program.methods
+        .withdrawLiquidity(new BN(amount))
+        .accountsPartial({
+          lp: lpKp.publicKey,
+          lpAccount,
+          lpTokenAccount,
+          globalState: globalStateKp.publicKey,
+          mint: mintKp.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([lpKp])
+        .rpc();
+
+    const before =
+      await program.provider.connection.getTokenAccountBalance(lpTokenAccount);
+    await setLiquidityLockPeriod(3600);
+    await assert.rejects(withdraw(500), /LiquidityLockNotElapsed/);
+    await setLiquidityLockPeriod(0);
+
+    // 1000 deposited with a 10% bonus: half the deposit takes half the bonus.
+    await withdraw(500);
+    let account = await program.account.liquidityAccount.fetch(lpAccount);
+    let state = await program.account.globalState.fetch(
+      globalStateKp.publicKey
+    );
+    assert(account.depositedAmount.eq(new BN(500)));
+    assert(account.liquidityProvided.eq(new BN(550)));
+    assert(state.totalLiquidity.eq(new BN(550)));
+
+    await assert.rejects(withdraw(600), /InsufficientLiquidity/);
+    await withdraw(500);
+    account = await program.account.liquidityAccount.fetch(lpAccount);
+    state = await program.account.globalState.fetch(globalStateKp.publicKey);
+    const balance =
+      await program.provider.connection.getTokenAccountBalance(lpTokenAccount);
+    assert(account.liquidityProvided.isZero());
+    assert(state.totalLiquidity.isZero());
+    assert.strictEqual(
+      Number(balance.value.amount) - Number(before.value.amount),
+      1000
+    );
+  });
+
+  it("record_fill_receipt credits venue-signed volume once", async () => {
+    const venueKp = new web3.Keypair();
+    const [tradeVenue] = web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("trade_venue"),
+        globalStateKp.publicKey.toBuffer(),
+        venueKp.publicKey.toBuffer(),
+      ],
+      program.programId
+    );
+    await program.methods
+      .addTradeVenue(venueKp.publicKey)
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        tradeVenue,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const receipt = {
+      receiptId: Array.from(web3.Keypair.generate().publicKey.toBytes()),
+      participant: lpKp.publicKey,
+      role: { liquidityProvider: {} },
+      volume: new BN(5000),
+    };
+    // global_state || receipt_id || participant || role || volume (u64 LE)
+    const sign = (volume: BN, signer = venueKp) =>
+      web3.Ed25519Program.createInstructionWithPrivateKey({
+        privateKey: signer.secretKey,
+        message: Buffer.concat([
+          globalStateKp.publicKey.toBuffer(),
+          Buffer.from(receipt.receiptId),
+          lpKp.publicKey.toBuffer(),
+          Buffer.from([1]),
+          volume.toArrayLike(Buffer, "le", 8),
+        ]),
+      });
+    const [fillRecord] = web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("fill_record"),
+        globalStateKp.publicKey.toBuffer(),
+        venueKp.publicKey.toBuffer(),
+        Buffer.from(receipt.receiptId),
+      ],
+      program.programId
+    );
+    const record = () =>
+      program.methods.recordFillReceipt(receipt).accountsPartial({
+        payer: admin,
+        globalState: globalStateKp.publicKey,
+        tradeVenue,
+        fillRecord,
+        traderAccount: null,
+        lpAccount,
+        instructions: web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: web3.SystemProgram.programId,
+      });
+
+    await assert.rejects(record().rpc(), /MissingFillReceiptSignature/);
+    await assert.rejects(
+      record()
+        .preInstructions([sign(new BN(9000))])
+        .rpc(),
+      /InvalidFillReceipt/
+    );
+    await assert.rejects(
+      record()
+        .preInstructions([sign(receipt.volume, new web3.Keypair())])
+        .rpc(),
+      /InvalidFillReceipt/
+    );
+
+    await record()
+      .preInstructions([sign(receipt.volume)])
+      .rpc();
+    const account = await program.account.liquidityAccount.fetch(lpAccount);
+    assert(account.tradeVolume.eq(new BN(5000)));
+
+    // The same receipt can't be counted twice.
+    await assert.rejects(
+      record()
+        .preInstructions([sign(receipt.volume)])
+        .rpc(),
+      /already in use/
+    );
+  });
+});
+
+describe("reward schedule", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.Zklr as anchor.Program<Zklr>;
+  const admin = program.provider.publicKey;
+  const globalStateKp = new web3.Keypair();
+  const rewardMintKp = new web3.Keypair();
+  const [rewardsAuthority] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("rewards_authority"), globalStateKp.publicKey.toBuffer()],
+    program.programId
+  );
+  const [rewardsVault] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("rewards_vault"), globalStateKp.publicKey.toBuffer()],
+    program.programId
+  );
+  const [rewardSchedule] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("reward_schedule"), globalStateKp.publicKey.toBuffer()],
+    program.programId
+  );
+  const scheduleParams = {
+    epochDuration: new BN(60),
+    initialEmission: new BN(6050),
+    decay: { halving: { interval: new BN(1) } },
+    lpShareBps: 2500,
+    funding: { mint: {} },
+  };
+  const scheduleAccounts = {
+    admin,
+    globalState: globalStateKp.publicKey,
+    rewardMint: rewardMintKp.publicKey,
+    rewardSchedule,
+    systemProgram: web3.SystemProgram.programId,
+  };
+
+  before(async () => {
+    const provider = program.provider as anchor.AnchorProvider;
+    await program.methods
+      .initialize(admin)
+      .accountsPartial({
+        globalState: globalStateKp.publicKey,
+        admin,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([globalStateKp])
+      .rpc();
+    await createMint(provider, rewardMintKp);
+    await program.methods
+      .initializeRewardsVault()
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        rewardMint: rewardMintKp.publicKey,
+        rewardsVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+  });
+
+  it("mint funding requires the rewards authority to mint", async () => {
+    await assert.rejects(
+      program.methods
+        .initializeRewardSchedule(scheduleParams)
+        .accountsPartial(scheduleAccounts)
+        .rpc(),
+      /InvalidRewardFunding/
+    );
+
+    // SetAuthority: tag 6, MintTokens (0), new authority.
+    const setAuthority = new web3.TransactionInstruction({
+      programId: TOKEN_PROGRAM_ID,
+      keys: [
+        { pubkey: rewardMintKp.publicKey, isSigner: false, isWritable: true },
+        { pubkey: admin, isSigner: true, isWritable: false },
+      ],
+      data: Buffer.concat([
+        Buffer.from([6, 0, 1]),
+        rewardsAuthority.toBuffer(),
+      ]),
+    });
+    await program.provider.sendAndConfirm(
+      new web3.Transaction().add(setAuthority)
+    );
+    await program.methods
+      .initializeRewardSchedule(scheduleParams)
+      .accountsPartial(scheduleAccounts)
+      .rpc();
+  });
+
+  it("advance_epoch mints and splits the epoch's emission", async () => {
+    const advance = () =>
+      program.methods
+        .advanceEpoch()
+        .accountsPartial({
+          globalState: globalStateKp.publicKey,
+          rewardSchedule,
+          rewardMint: rewardMintKp.publicKey,
+          rewardsVault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+    const { nextEpochStart } =
+      await program.account.rewardSchedule.fetch(rewardSchedule);
+    await advance();
+
+    // 6050 doesn't stream evenly over 60 seconds: 6000 is released and the
+    // remaining 50 is carried into the next epoch.
+    const vault =
+      await program.provider.connection.getTokenAccountBalance(rewardsVault);
+    const state = await program.account.globalState.fetch(
+      globalStateKp.publicKey
+    );
+    const schedule =
+      await program.account.rewardSchedule.fetch(rewardSchedule);
+    assert.strictEqual(vault.value.amount, "6000");
+    assert(state.lpRewardRate.eq(new BN(25)));
+    assert(state.traderRewardRate.eq(new BN(75)));
+    assert(schedule.epoch.eq(new BN(1)));
+    assert(schedule.totalReleased.eq(new BN(6000)));
+    assert(schedule.carriedEmission.eq(new BN(50)));
+    assert(state.rewardsOwed.eq(new BN(6000)));
+    // The epoch runs from its scheduled start, however late the crank is.
+    assert(state.lastRewardTimestamp.eq(nextEpochStart));
+    assert(schedule.nextEpochStart.eq(nextEpochStart.addn(60)));
+
+    await assert.rejects(advance(), /EpochNotElapsed/);
+  });
+});
+
+describe("vault reward funding", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.Zklr as anchor.Program<Zklr>;
+  const admin = program.provider.publicKey;
+  const globalStateKp = new web3.Keypair();
+  const rewardMintKp = new web3.Keypair();
+  const [rewardsVault] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("rewards_vault"), globalStateKp.publicKey.toBuffer()],
+    program.programId
+  );
+  const [rewardSchedule] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("reward_schedule"), globalStateKp.publicKey.toBuffer()],
+    program.programId
+  );
+
+  before(async () => {
+    const provider = program.provider as anchor.AnchorProvider;
+    await program.methods
+      .initialize(admin)
+      .accountsPartial({
+        globalState: globalStateKp.publicKey,
+        admin,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([globalStateKp])
+      .rpc();
+    await createMint(provider, rewardMintKp);
+    await program.methods
+      .initializeRewardsVault()
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        rewardMint: rewardMintKp.publicKey,
+        rewardsVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+    await program.methods
+      .initializeRewardSchedule({
+        epochDuration: new BN(60),
+        initialEmission: new BN(6000),
+        decay: { constant: {} },
+        lpShareBps: 2500,
+        funding: { vault: {} },
+      })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        rewardMint: rewardMintKp.publicKey,
+        rewardSchedule,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("advance_epoch requires the vault to cover the release", async () => {
+    const provider = program.provider as anchor.AnchorProvider;
+    const advance = () =>
+      program.methods
+        .advanceEpoch()
+        .accountsPartial({
+          globalState: globalStateKp.publicKey,
+          rewardSchedule,
+          rewardMint: rewardMintKp.publicKey,
+          rewardsVault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+    await mintTo(provider, rewardMintKp.publicKey, rewardsVault, 5999);
+    await assert.rejects(advance(), /InsufficientRewardsVault/);
+
+    await mintTo(provider, rewardMintKp.publicKey, rewardsVault, 1);
+    await advance();
+    const state = await program.account.globalState.fetch(
+      globalStateKp.publicKey
+    );
+    assert(state.rewardsOwed.eq(new BN(6000)));
+  });
+});
+
+describe("groth16 priority proofs", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.Zklr as anchor.Program<Zklr>;
+  const admin = program.provider.publicKey;
+  // Fixed vectors generated off-chain with arkworks (ark-groth16 0.4, BN254)
+  // by tests/fixtures/gen. Proofs are bound to the trader and global state, so
+  // both keypairs come from the fixture's seeds.
+  const fixture = require("./fixtures/groth16_priority.json");
+  const hex = (s: string) => Buffer.from(s, "hex");
+  const keypair = (name: string) =>
+    web3.Keypair.fromSeed(hex(fixture.keys[name]));
+  const vector = fixture.priority.proofs.groth16;
+  const globalStateKp = keypair(vector.globalState);
+  const traderKp = keypair(vector.trader);
+  const trader = traderKp.publicKey;
+  const mintKp = new web3.Keypair();
+  const commitment = Array.from(hex(vector.commitment));
+  const nullifier = Array.from(hex(vector.nullifier));
+  const proof = hex(vector.proof);
+
+  const PRIORITY_CIRCUIT_ID = 0;
+  const VK_VERSION = 1;
+  const u32 = (value: number) => new BN(value).toArrayLike(Buffer, "le", 4);
+  const [verifyingKey] = web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("verifying_key"),
+      globalStateKp.publicKey.toBuffer(),
+      u32(PRIORITY_CIRCUIT_ID),
+      u32(VK_VERSION),
+    ],
+    program.programId
+  );
+  const [traderAccount] = web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("trader"),
+      globalStateKp.publicKey.toBuffer(),
+      trader.toBuffer(),
+    ],
+    program.programId
+  );
+  const [treasury] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("treasury"), globalStateKp.publicKey.toBuffer()],
+    program.programId
+  );
+  const [treasuryVault] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("treasury_vault"), globalStateKp.publicKey.toBuffer()],
+    program.programId
+  );
+  const oracleKp = new web3.Keypair();
+  const [latencyOracle] = web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("latency_oracle"),
+      globalStateKp.publicKey.toBuffer(),
+      oracleKp.publicKey.toBuffer(),
+    ],
+    program.programId
+  );
+  const verifyAccounts = {
+    trader,
+    traderAccount,
+    globalState: globalStateKp.publicKey,
+    verifyingKey,
+    mint: mintKp.publicKey,
+    tokenProgram: TOKEN_PROGRAM_ID,
+    latencyOracle,
+    latencyChallenge: null,
+  };
+  // Latency for calls that never reach the attestation check.
+  const unattested = {
+    oracle: [{ latency: new BN(10), slot: new BN(0), expiry: new BN(0) }],
+  };
+  const [protocolConfig] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("protocol_config"), globalStateKp.publicKey.toBuffer()],
+    program.programId
+  );
+  const [commitmentTree] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("commitment_tree"), globalStateKp.publicKey.toBuffer()],
+    program.programId
+  );
+  const u64 = (value: BN) => value.toArrayLike(Buffer, "le", 8);
+  const bandwidthEpoch = (epoch: BN) =>
+    web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("bandwidth_epoch"),
+        globalStateKp.publicKey.toBuffer(),
+        u64(epoch),
+      ],
+      program.programId
+    )[0];
+  const bandwidthTicket = (epoch: BN) =>
+    web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("bandwidth_ticket"),
+        globalStateKp.publicKey.toBuffer(),
+        trader.toBuffer(),
+        u64(epoch),
+      ],
+      program.programId
+    )[0];
+  // The committed order, revealed against the market's order book.
+  const order = {
+    version: fixture.order.version,
+    market: new web3.PublicKey(hex(fixture.order.market)),
+    side: { bid: {} },
+    price: new BN(fixture.order.price),
+    size: new BN(fixture.order.size),
+    salt: Array.from(hex(fixture.order.salt)),
+  };
+  const [orderBook] = web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("order_book"),
+      globalStateKp.publicKey.toBuffer(),
+      order.market.toBuffer(),
+    ],
+    program.programId
+  );
+  const [orderRangeKey] = web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("verifying_key"),
+      globalStateKp.publicKey.toBuffer(),
+      u32(1),
+      u32(1),
+    ],
+    program.programId
+  );
+  const traderAllocation = (epoch: BN) =>
+    web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("trader_allocation"),
+        globalStateKp.publicKey.toBuffer(),
+        trader.toBuffer(),
+        u64(epoch),
+      ],
+      program.programId
+    )[0];
+  const openAccounts = (epoch: BN) => ({
+    payer: admin,
+    globalState: globalStateKp.publicKey,
+    bandwidthEpoch: bandwidthEpoch(epoch),
+    systemProgram: web3.SystemProgram.programId,
+  });
+  const allocateAccounts = (epoch: BN) => ({
+    trader,
+    traderAccount,
+    globalState: globalStateKp.publicKey,
+    bandwidthEpoch: bandwidthEpoch(epoch),
+    bandwidthTicket: bandwidthTicket(epoch),
+    traderAllocation: traderAllocation(epoch),
+    systemProgram: web3.SystemProgram.programId,
+  });
+  const finalizeAccounts = (epoch: BN) => ({
+    globalState: globalStateKp.publicKey,
+    bandwidthEpoch: bandwidthEpoch(epoch),
+    bandwidthTicket: bandwidthTicket(epoch),
+  });
+  const blockTime = async () => {
+    const connection = program.provider.connection;
+    return await connection.getBlockTime(await connection.getSlot());
+  };
+  const PAUSE_STAKING = 1 << 0;
+  const PAUSE_BANDWIDTH = 1 << 2;
+  const setPaused = (paused: number) =>
+    program.methods
+      .setPaused(paused)
+      .accountsPartial({ admin, globalState: globalStateKp.publicKey })
+      .rpc();
+  // Latency attestation and the Ed25519 instruction carrying its signature.
+  const attest = async (
+    latency: number,
+    signer = oracleKp,
+    subject = trader
+  ) => {
+    const attestation = {
+      latency: new BN(latency),
+      slot: new BN(await program.provider.connection.getSlot()),
+      expiry: new BN((await blockTime()) + 60),
+    };
+    const message = Buffer.concat([
+      globalStateKp.publicKey.toBuffer(),
+      subject.toBuffer(),
+      u64(attestation.latency),
+      u64(attestation.slot),
+      u64(attestation.expiry),
+    ]);
+    const ix = web3.Ed25519Program.createInstructionWithPrivateKey({
+      privateKey: signer.secretKey,
+      message,
+    });
+    return { attestation, ix };
+  };
+
+  before(async () => {
+    await program.methods
+      .initialize(admin)
+      .accountsPartial({
+        globalState: globalStateKp.publicKey,
+        admin,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([globalStateKp])
+      .rpc();
+
+    const sig = await program.provider.connection.requestAirdrop(
+      trader,
+      web3.LAMPORTS_PER_SOL
+    );
+    await program.provider.connection.confirmTransaction(sig);
+
+    await createMint(program.provider as anchor.AnchorProvider, mintKp);
+    await program.methods
+      .initializeVaults({ spl: {} })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        mint: mintKp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+
+    // 25% of slashed stake goes to the insurance reserve.
+    await program.methods
+      .initializeTreasury(2500)
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        mint: mintKp.publicKey,
+        treasury,
+        treasuryVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+
+    await program.methods
+      .initializeCommitmentTree()
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        commitmentTree,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // The key's points don't fit in one transaction.
+    const vk = fixture.priority.verifyingKey;
+    const ic = vk.ic.map((point: string) => Array.from(hex(point)));
+    await program.methods
+      .addVerifyingKey(PRIORITY_CIRCUIT_ID, VK_VERSION, {
+        alphaG1: Array.from(hex(vk.alphaG1)),
+        betaG2: Array.from(hex(vk.betaG2)),
+        gammaG2: Array.from(hex(vk.gammaG2)),
+        deltaG2: Array.from(hex(vk.deltaG2)),
+        ic: ic.slice(0, 5),
+      })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        verifyingKey,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .extendVerifyingKey(ic.slice(5))
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        verifyingKey,
+      })
+      .rpc();
+
+    await program.methods
+      .addLatencyOracle(oracleKp.publicKey)
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        latencyOracle,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .registerTrader()
+      .accountsPartial({
+        trader,
+        traderAccount,
+        globalState: globalStateKp.publicKey,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([traderKp])
+      .rpc();
+  });
+
+  it("rejects proofs until the verifying key is activated", async () => {
+    await assert.rejects(
+      program.methods
+        .verifyPriority(proof, commitment, nullifier, unattested, VK_VERSION)
+        .accountsPartial(verifyAccounts)
+        .signers([traderKp])
+        .rpc(),
+      /VerifyingKeyNotActive/
+    );
+
+    await program.methods
+      .activateVerifyingKey()
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        verifyingKey,
+      })
+      .rpc();
+  });
+
+  it("records a proof for a different commitment", async () => {
+    const otherCommitment = [...commitment];
+    otherCommitment[31] ^= 1;
+    await program.methods
+      .verifyPriority(proof, otherCommitment, nullifier, unattested, VK_VERSION)
+      .accountsPartial(verifyAccounts)
+      .signers([traderKp])
+      .rpc();
+
+    // The attempt is recorded, but neither the commitment nor the nullifier
+    // is taken.
+    const [proofNullifier] = web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("nullifier"),
+        globalStateKp.publicKey.toBuffer(),
+        u32(PRIORITY_CIRCUIT_ID),
+        Buffer.from(nullifier),
+      ],
+      program.programId
+    );
+    const account = await program.account.traderAccount.fetch(traderAccount);
+    assert.strictEqual(account.invalidProofAttempts, 1);
+    assert.notDeepStrictEqual(account.commitment, otherCommitment);
+    assert.strictEqual(
+      await program.provider.connection.getAccountInfo(proofNullifier),
+      null
+    );
+  });
+
+  it("records a proof generated for another trader", async () => {
+    const other = fixture.priority.proofs.latency;
+    await program.methods
+      .verifyPriority(
+        hex(other.proof),
+        Array.from(hex(other.commitment)),
+        Array.from(hex(other.nullifier)),
+        unattested,
+        VK_VERSION
+      )
+      .accountsPartial(verifyAccounts)
+      .signers([traderKp])
+      .rpc();
+
+    const account = await program.account.traderAccount.fetch(traderAccount);
+    assert.strictEqual(account.invalidProofAttempts, 2);
+  });
+
+  it("slashes a trader after repeated invalid proofs", async () => {
+    const slashAccounts = {
+      globalState: globalStateKp.publicKey,
+      traderAccount,
+      treasury,
+      treasuryVault,
+      mint: mintKp.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    await assert.rejects(
+      program.methods.slashTrader().accountsPartial(slashAccounts).rpc(),
+      /SlashThresholdNotReached/
+    );
+
+    // A malformed proof is the third, reaching the default threshold.
+    await program.methods
+      .verifyPriority(
+        proof.subarray(0, 128),
+        commitment,
+        nullifier,
+        unattested,
+        VK_VERSION
+      )
+      .accountsPartial(verifyAccounts)
+      .signers([traderKp])
+      .rpc();
+    let account = await program.account.traderAccount.fetch(traderAccount);
+    assert.strictEqual(account.invalidProofAttempts, 3);
+
+    await program.methods.slashTrader().accountsPartial(slashAccounts).rpc();
+    account = await program.account.traderAccount.fetch(traderAccount);
+    assert.strictEqual(account.invalidProofAttempts, 0);
+  });
+
+  it("only accepts latency signed by a registered oracle", async () => {
+    const { attestation } = await attest(10);
+    await assert.rejects(
+      program.methods
+        .verifyPriority(
+          proof,
+          commitment,
+          nullifier,
+          { oracle: [attestation] },
+          VK_VERSION
+        )
+        .accountsPartial(verifyAccounts)
+        .signers([traderKp])
+        .rpc(),
+      /MissingLatencyAttestation/
+    );
+
+    const forged = await attest(0, new web3.Keypair());
+    await assert.rejects(
+      program.methods
+        .verifyPriority(
+          proof,
+          commitment,
+          nullifier,
+          { oracle: [forged.attestation] },
+          VK_VERSION
+        )
+        .accountsPartial(verifyAccounts)
+        .preInstructions([forged.ix])
+        .signers([traderKp])
+        .rpc(),
+      /InvalidLatencyAttestation/
+    );
+
+    // A signed attestation for a different latency doesn't match.
+    const signed = await attest(10);
+    await assert.rejects(
+      program.methods
+        .verifyPriority(
+          proof,
+          commitment,
+          nullifier,
+          { oracle: [{ ...signed.attestation, latency: new BN(0) }] },
+          VK_VERSION
+        )
+        .accountsPartial(verifyAccounts)
+        .preInstructions([signed.ix])
+        .signers([traderKp])
+        .rpc(),
+      /InvalidLatencyAttestation/
+    );
+  });
+
+  it("accepts a valid proof and stores the commitment", async () => {
+    const provider = program.provider as anchor.AnchorProvider;
+    const traderTokenAccount = await createTokenAccount(
+      provider,
+      mintKp.publicKey,
+      trader
+    );
+    await mintTo(provider, mintKp.publicKey, traderTokenAccount, 1000);
+    await program.methods
+      .stake({ plain: [new BN(1000)] })
+      .accountsPartial({
+        trader,
+        traderTokenAccount,
+        traderAccount,
+        globalState: globalStateKp.publicKey,
+        mint: mintKp.publicKey,
+        equalityProof: null,
+        ciphertextValidityProof: null,
+        rangeProof: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([traderKp])
+      .rpc();
+
+    const { attestation, ix } = await attest(10);
+    await program.methods
+      .verifyPriority(
+        proof,
+        commitment,
+        nullifier,
+        { oracle: [attestation] },
+        VK_VERSION
+      )
+      .accountsPartial(verifyAccounts)
+      .preInstructions([ix])
+      .signers([traderKp])
+      .rpc();
+
+    const account = await program.account.traderAccount.fetch(traderAccount);
+    assert.deepStrictEqual(account.commitment, commitment);
+    assert(account.speedMultiplier.eq(new BN(90)));
+  });
+
+  it("burns the verification fee out of the stake vault", async () => {
+    const [stakeVault] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("stake_vault"), globalStateKp.publicKey.toBuffer()],
+      program.programId
+    );
+    const vault =
+      await program.provider.connection.getTokenAccountBalance(stakeVault);
+    const state = await program.account.globalState.fetch(
+      globalStateKp.publicKey
+    );
+    const account = await program.account.traderAccount.fetch(traderAccount);
+    assert.strictEqual(vault.value.amount, "990");
+    assert(state.totalStaked.eq(new BN(990)));
+    assert(account.stakedAmount.eq(new BN(990)));
+  });
+
+  it("appends accepted commitments to the commitment tree", async () => {
+    // Root of a depth-20 tree holding only the commitment, as leaf 0.
+    const sha256 = (left: Buffer, right: Buffer) =>
+      createHash("sha256").update(left).update(right).digest();
+    let zero = Buffer.alloc(32);
+    let root = Buffer.from(commitment);
+    for (let level = 0; level < 20; level++) {
+      root = sha256(root, zero);
+      zero = sha256(zero, zero);
+    }
+
+    const tree = await program.account.commitmentTree.fetch(commitmentTree);
+    assert.strictEqual(tree.nextIndex, 1);
+    assert.strictEqual(tree.currentRootIndex, 1);
+    assert.deepStrictEqual(tree.roots[0], Array.from(zero));
+    assert.deepStrictEqual(tree.roots[1], Array.from(root));
+  });
+
+  it("rejects a replayed proof", async () => {
+    const [proofNullifier] = web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("nullifier"),
+        globalStateKp.publicKey.toBuffer(),
+        u32(PRIORITY_CIRCUIT_ID),
+        Buffer.from(nullifier),
+      ],
+      program.programId
+    );
+    const record =
+      await program.account.proofNullifier.fetch(proofNullifier);
+    assert.deepStrictEqual(record.nullifier, nullifier);
+    assert.deepStrictEqual(record.commitment, commitment);
+
+    await assert.rejects(
+      program.methods
+        .verifyPriority(proof, commitment, nullifier, unattested, VK_VERSION)
+        .accountsPartial(verifyAccounts)
+        .signers([traderKp])
+        .rpc(),
+      /ProofReplayed/
+    );
+  });
+
+  it("allocate_bandwidth registers a ticket for the next epoch", async () => {
+    const now = await blockTime();
+    const epoch = new BN(Math.floor(now / 300) + 1);
+
+    await assert.rejects(
+      program.methods
+        .openBandwidthEpoch(epoch.subn(1))
+        .accountsPartial(openAccounts(epoch.subn(1)))
+        .rpc(),
+      /InvalidBandwidthEpoch/
+    );
+    await program.methods
+      .openBandwidthEpoch(epoch)
+      .accountsPartial(openAccounts(epoch))
+      .rpc();
+    await program.methods
+      .allocateBandwidth(epoch)
+      .accountsPartial(allocateAccounts(epoch))
+      .signers([traderKp])
+      .rpc();
+
+    const state = await program.account.bandwidthEpoch.fetch(
+      bandwidthEpoch(epoch)
+    );
+    const ticket = await program.account.bandwidthTicket.fetch(
+      bandwidthTicket(epoch)
+    );
+    const account = await program.account.traderAccount.fetch(traderAccount);
+    assert(ticket.trader.equals(trader));
+    assert(ticket.epoch.eq(epoch));
+    assert(ticket.priority.gtn(0));
+    assert(!ticket.finalized);
+    assert(ticket.windowStart.eq(epoch.muln(300)));
+    assert(ticket.windowEnd.eq(epoch.addn(1).muln(300)));
+    assert(ticket.expiresAt.eq(BN.min(ticket.windowEnd, account.proofExpiry)));
+    assert(state.capacity.eq(new BN(1000)));
+    assert(state.totalPriority.eq(ticket.priority));
+    assert.strictEqual(state.traderCount, 1);
+
+    // One allocation per trader per epoch.
+    await assert.rejects(
+      program.methods
+        .allocateBandwidth(epoch)
+        .accountsPartial(allocateAccounts(epoch))
+        .signers([traderKp])
+        .rpc()
+    );
+    await assert.rejects(
+      program.methods
+        .finalizeBandwidthShare()
+        .accountsPartial(finalizeAccounts(epoch))
+        .rpc(),
+      /BandwidthRegistrationOpen/
+    );
+    const closeTicket = () =>
+      program.methods
+        .closeBandwidthTicket()
+        .accountsPartial({
+          trader,
+          globalState: globalStateKp.publicKey,
+          bandwidthTicket: bandwidthTicket(epoch),
+        })
+        .signers([traderKp])
+        .rpc();
+    await assert.rejects(closeTicket(), /BandwidthTicketActive/);
+
+    // Closing tickets is paused with the rest of the bandwidth market.
+    await setPaused(PAUSE_BANDWIDTH);
+    await assert.rejects(closeTicket(), /ProtocolPaused/);
+    await setPaused(0);
+  });
+
+  it("finalize_bandwidth_share hands out the epoch's capacity", async () => {
+    const { bump, ...params } =
+      await program.account.protocolConfig.fetch(protocolConfig);
+    await program.methods
+      .updateConfig({ ...params, bandwidthEpochDuration: new BN(60) })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        protocolConfig,
+      })
+      .rpc();
+
+    const epoch = new BN(Math.floor((await blockTime()) / 60) + 1);
+    await program.methods
+      .openBandwidthEpoch(epoch)
+      .accountsPartial(openAccounts(epoch))
+      .rpc();
+    await program.methods
+      .allocateBandwidth(epoch)
+      .accountsPartial(allocateAccounts(epoch))
+      .signers([traderKp])
+      .rpc();
+
+    // Wait for registration to close.
+    while ((await blockTime()) < epoch.muln(60).toNumber()) {
+      await new Promise((resolve) => setTimeout(resolve, 1000));
+    }
+    await assert.rejects(
+      program.methods
+        .allocateBandwidth(epoch)
+        .accountsPartial(allocateAccounts(epoch))
+        .signers([traderKp])
+        .rpc(),
+      /BandwidthRegistrationClosed/
+    );
+    await program.methods
+      .finalizeBandwidthShare()
+      .accountsPartial(finalizeAccounts(epoch))
+      .rpc();
+
+    // The only registered trader gets the whole capacity.
+    const ticket = await program.account.bandwidthTicket.fetch(
+      bandwidthTicket(epoch)
+    );
+    assert(ticket.finalized);
+    assert(ticket.share.eq(new BN(1000)));
+  });
+
+  it("pool members claim slots without revealing themselves", async () => {
+    const [executionPool] = web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("execution_pool"),
+        globalStateKp.publicKey.toBuffer(),
+        u32(0),
+      ],
+      program.programId
+    );
+    await program.methods
+      .createExecutionPool(0)
+      .accountsPartial({
+        creator: admin,
+        globalState: globalStateKp.publicKey,
+        executionPool,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // Commitment to a member secret known only to the trader.
+    const memberCommitment = Array.from(
+      createHash("sha256").update("pool member secret").digest()
+    );
+    const joinAccounts = {
+      trader,
+      traderAccount,
+      globalState: globalStateKp.publicKey,
+      executionPool,
+    };
+    await program.methods
+      .joinExecutionPool(memberCommitment)
+      .accountsPartial(joinAccounts)
+      .signers([traderKp])
+      .rpc();
+    await assert.rejects(
+      program.methods
+        .joinExecutionPool(memberCommitment)
+        .accountsPartial(joinAccounts)
+        .signers([traderKp])
+        .rpc(),
+      /AlreadyPoolMember/
+    );
+
+    const pool = await program.account.executionPool.fetch(executionPool);
+    const root = createHash("sha256")
+      .update(Buffer.alloc(32))
+      .update(Buffer.from(memberCommitment))
+      .digest();
+    assert(pool.members[0].equals(trader));
+    assert.deepStrictEqual(pool.membershipRoot, Array.from(root));
+
+    // The trader registered under another instance.
+    const foreignStateKp = new web3.Keypair();
+    await program.methods
+      .initialize(admin)
+      .accountsPartial({
+        globalState: foreignStateKp.publicKey,
+        admin,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([foreignStateKp])
+      .rpc();
+    const [foreignTraderAccount] = web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("trader"),
+        foreignStateKp.publicKey.toBuffer(),
+        trader.toBuffer(),
+      ],
+      program.programId
+    );
+    await program.methods
+      .registerTrader()
+      .accountsPartial({
+        trader,
+        traderAccount: foreignTraderAccount,
+        globalState: foreignStateKp.publicKey,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([traderKp])
+      .rpc();
+
+    const epoch = new BN(Math.floor((await blockTime()) / 60) + 1);
+    const [poolTicket] = web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("bandwidth_ticket"),
+        globalStateKp.publicKey.toBuffer(),
+        executionPool.toBuffer(),
+        u64(epoch),
+      ],
+      program.programId
+    );
+    const [poolAllocation] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_allocation"), executionPool.toBuffer(), u64(epoch)],
+      program.programId
+    );
+    await program.methods
+      .openBandwidthEpoch(epoch)
+      .accountsPartial(openAccounts(epoch))
+      .rpc();
+    const poolAccounts = {
+      payer: admin,
+      globalState: globalStateKp.publicKey,
+      executionPool,
+      bandwidthEpoch: bandwidthEpoch(epoch),
+      bandwidthTicket: poolTicket,
+      poolAllocation,
+      systemProgram: web3.SystemProgram.programId,
+    };
+
+    // Its trader account doesn't count for this instance's pool.
+    await assert.rejects(
+      program.methods
+        .allocatePoolBandwidth(epoch)
+        .accountsPartial(poolAccounts)
+        .remainingAccounts([
+          {
+            pubkey: foreignTraderAccount,
+            isSigner: false,
+            isWritable: false,
+          },
+          {
+            pubkey: traderAllocation(epoch),
+            isSigner: false,
+            isWritable: true,
+          },
+        ])
+        .rpc(),
+      /InvalidPoolMembers/
+    );
+
+    await program.methods
+      .allocatePoolBandwidth(epoch)
+      .accountsPartial(poolAccounts)
+      .remainingAccounts([
+        { pubkey: traderAccount, isSigner: false, isWritable: false },
+        {
+          pubkey: traderAllocation(epoch),
+          isSigner: false,
+          isWritable: true,
+        },
+      ])
+      .rpc();
+
+    const ticket = await program.account.bandwidthTicket.fetch(poolTicket);
+    const allocation =
+      await program.account.poolAllocation.fetch(poolAllocation);
+    const marker = await program.account.traderAllocation.fetch(
+      traderAllocation(epoch)
+    );
+    assert(ticket.trader.equals(executionPool));
+    assert(ticket.priority.gtn(0));
+    assert.deepStrictEqual(allocation.membershipRoot, Array.from(root));
+    assert.strictEqual(allocation.memberCount, 1);
+    assert(marker.trader.equals(trader));
+    assert(marker.allocatedTo.equals(executionPool));
+
+    // The member's priority already counts for the epoch through the pool.
+    await assert.rejects(
+      program.methods
+        .allocateBandwidth(epoch)
+        .accountsPartial(allocateAccounts(epoch))
+        .signers([traderKp])
+        .rpc(),
+      /BandwidthAlreadyAllocated/
+    );
+
+    // Slots can only be claimed from a finalized share.
+    const vk = fixture.poolMembership.verifyingKey;
+    const [membershipKey] = web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("verifying_key"),
+        globalStateKp.publicKey.toBuffer(),
+        u32(2),
+        u32(1),
+      ],
+      program.programId
+    );
+    await program.methods
+      .addVerifyingKey(2, 1, {
+        alphaG1: Array.from(hex(vk.alphaG1)),
+        betaG2: Array.from(hex(vk.betaG2)),
+        gammaG2: Array.from(hex(vk.gammaG2)),
+        deltaG2: Array.from(hex(vk.deltaG2)),
+        ic: vk.ic.map((point: string) => Array.from(hex(point))),
+      })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        verifyingKey: membershipKey,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .activateVerifyingKey()
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        verifyingKey: membershipKey,
+      })
+      .rpc();
+    const membership = fixture.poolMembership;
+    assert.strictEqual(membership.membershipRoot, root.toString("hex"));
+    const nullifier = Array.from(hex(membership.nullifier));
+    const [slotClaim] = web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("slot_claim"),
+        executionPool.toBuffer(),
+        u64(epoch),
+        Buffer.from(nullifier),
+      ],
+      program.programId
+    );
+    // Any wallet can submit the claim, so it doesn't reveal the member.
+    const claim = () =>
+      program.methods
+        .claimExecutionSlot(epoch, nullifier, hex(membership.proof), 1)
+        .accountsPartial({
+          claimant: admin,
+          globalState: globalStateKp.publicKey,
+          poolAllocation,
+          bandwidthTicket: poolTicket,
+          verifyingKey: membershipKey,
+          slotClaim,
+          systemProgram: web3.SystemProgram.programId,
+        })
+        .rpc();
+    await assert.rejects(claim(), /BandwidthShareNotFinalized/);
+
+    while ((await blockTime()) < epoch.muln(60).toNumber()) {
+      await new Promise((resolve) => setTimeout(resolve, 1000));
+    }
+    await program.methods
+      .finalizeBandwidthShare()
+      .accountsPartial({
+        globalState: globalStateKp.publicKey,
+        bandwidthEpoch: bandwidthEpoch(epoch),
+        bandwidthTicket: poolTicket,
+      })
+      .rpc();
+    await claim();
+
+    // The pool is the only registration, and its one member takes it all.
+    const slot = await program.account.slotClaim.fetch(slotClaim);
+    assert(slot.pool.equals(executionPool));
+    assert(slot.epoch.eq(epoch));
+    assert.deepStrictEqual(slot.nullifier, nullifier);
+    assert(slot.claimant.equals(admin));
+    assert(slot.units.eq(new BN(1000)));
+    assert(slot.windowStart.eq(epoch.muln(60)));
+    await assert.rejects(claim());
+
+    // Leaving the pool removes the member and rebuilds the root.
+    await program.methods
+      .leaveExecutionPool()
+      .accountsPartial(joinAccounts)
+      .signers([traderKp])
+      .rpc();
+    const left = await program.account.executionPool.fetch(executionPool);
+    assert.strictEqual(left.members.length, 0);
+    assert.strictEqual(left.memberCommitments.length, 0);
+    assert.deepStrictEqual(left.membershipRoot, Array(32).fill(0));
+    await assert.rejects(
+      program.methods
+        .leaveExecutionPool()
+        .accountsPartial(joinAccounts)
+        .signers([traderKp])
+        .rpc(),
+      /NotPoolMember/
+    );
+  });
+
+  it("slashes repeated invalid reveals into the treasury", async () => {
+    const { bump, ...params } =
+      await program.account.protocolConfig.fetch(protocolConfig);
+    await program.methods
+      .updateConfig({ ...params, maxInvalidProofs: 1, revealDelay: new BN(0) })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        protocolConfig,
+      })
+      .rpc();
+    const slashAccounts = {
+      globalState: globalStateKp.publicKey,
+      traderAccount,
+      treasury,
+      treasuryVault,
+      mint: mintKp.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    await assert.rejects(
+      program.methods.slashTrader().accountsPartial(slashAccounts).rpc(),
+      /SlashThresholdNotReached/
+    );
+
+    // An order book and an order range key to reveal against.
+    await program.methods
+      .initializeOrderBook(order.market)
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        orderBook,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+    const vk = fixture.orderRange.verifyingKey;
+    await program.methods
+      .addVerifyingKey(1, 1, {
+        alphaG1: Array.from(hex(vk.alphaG1)),
+        betaG2: Array.from(hex(vk.betaG2)),
+        gammaG2: Array.from(hex(vk.gammaG2)),
+        deltaG2: Array.from(hex(vk.deltaG2)),
+        ic: vk.ic.map((point: string) => Array.from(hex(point))),
+      })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        verifyingKey: orderRangeKey,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .activateVerifyingKey()
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        verifyingKey: orderRangeKey,
+      })
+      .rpc();
+
+    // An order that doesn't match the commitment is an invalid reveal,
+    // recorded without failing the transaction.
+    await program.methods
+      .revealTrade(
+        { ...order, price: new BN(1) },
+        hex(fixture.orderRange.proof),
+        1
+      )
+      .accountsPartial({
+        trader,
+        traderAccount,
+        globalState: globalStateKp.publicKey,
+        verifyingKey: orderRangeKey,
+        orderBook,
+      })
+      .signers([traderKp])
+      .rpc();
+    let account = await program.account.traderAccount.fetch(traderAccount);
+    assert.strictEqual(account.invalidProofAttempts, 1);
+
+    // Slashing is paused with staking.
+    await setPaused(PAUSE_STAKING);
+    await assert.rejects(
+      program.methods.slashTrader().accountsPartial(slashAccounts).rpc(),
+      /ProtocolPaused/
+    );
+    await setPaused(0);
+
+    // Anyone can slash once the threshold is reached.
+    await program.methods.slashTrader().accountsPartial(slashAccounts).rpc();
+
+    // 20% of the 990 stake is slashed, 25% of which is insurance.
+    const vault =
+      await program.provider.connection.getTokenAccountBalance(treasuryVault);
+    account = await program.account.traderAccount.fetch(traderAccount);
+    const state = await program.account.treasury.fetch(treasury);
+    assert.strictEqual(vault.value.amount, "198");
+    assert(account.stakedAmount.eq(new BN(792)));
+    assert.strictEqual(account.invalidProofAttempts, 0);
+    assert(state.insuranceBalance.eq(new BN(49)));
+    assert(state.revenueBalance.eq(new BN(149)));
+  });
+
+  it("reveal_trade places the committed order in the book", async () => {
+    const revealAccounts = {
+      trader,
+      traderAccount,
+      globalState: globalStateKp.publicKey,
+      verifyingKey: orderRangeKey,
+      orderBook,
+    };
+    await program.methods
+      .revealTrade(order, hex(fixture.orderRange.proof), 1)
+      .accountsPartial(revealAccounts)
+      .signers([traderKp])
+      .rpc();
+
+    let book = await program.account.orderBook.fetch(orderBook);
+    const account = await program.account.traderAccount.fetch(traderAccount);
+    assert.strictEqual(book.bidCount, 1);
+    assert.strictEqual(book.askCount, 0);
+    assert(book.bids[0].trader.equals(trader));
+    assert.deepStrictEqual(book.bids[0].commitment, commitment);
+    assert(book.bids[0].price.eq(order.price));
+    assert(book.bids[0].size.eq(order.size));
+    assert(book.bids[0].priority.gtn(0));
+    assert.deepStrictEqual(account.commitment, Array(32).fill(0));
+    assert.strictEqual(account.invalidProofAttempts, 0);
+
+    // The commitment is spent.
+    await assert.rejects(
+      program.methods
+        .revealTrade(order, hex(fixture.orderRange.proof), 1)
+        .accountsPartial(revealAccounts)
+        .signers([traderKp])
+        .rpc(),
+      /NoOrderCommitment/
+    );
+
+    // Only the order's trader can cancel it.
+    const sequence = book.bids[0].sequence;
+    await assert.rejects(
+      program.methods
+        .cancelOrder({ bid: {} }, sequence)
+        .accountsPartial({
+          trader: admin,
+          globalState: globalStateKp.publicKey,
+          orderBook,
+        })
+        .rpc(),
+      /OrderNotFound/
+    );
+    await program.methods
+      .cancelOrder({ bid: {} }, sequence)
+      .accountsPartial({
+        trader,
+        globalState: globalStateKp.publicKey,
+        orderBook,
+      })
+      .signers([traderKp])
+      .rpc();
+    book = await program.account.orderBook.fetch(orderBook);
+    assert.strictEqual(book.bidCount, 0);
+  });
+
+  it("clears crossing orders at one price in priority order", async () => {
+    const provider = program.provider as anchor.AnchorProvider;
+    const { bump, ...params } =
+      await program.account.protocolConfig.fetch(protocolConfig);
+    await program.methods
+      .updateConfig({ ...params, auctionBatchDuration: new BN(5) })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        protocolConfig,
+      })
+      .rpc();
+    const market = new web3.PublicKey(hex(fixture.auction.market));
+    const [auctionBook] = web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("order_book"),
+        globalStateKp.publicKey.toBuffer(),
+        market.toBuffer(),
+      ],
+      program.programId
+    );
+    await program.methods
+      .initializeOrderBook(market)
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        orderBook: auctionBook,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // Each trader stakes and proves a commitment to their order.
+    const traders = [];
+    for (const entry of fixture.auction.orders) {
+      const kp = keypair(entry.trader);
+      const [account] = web3.PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("trader"),
+          globalStateKp.publicKey.toBuffer(),
+          kp.publicKey.toBuffer(),
+        ],
+        program.programId
+      );
+      const sig = await provider.connection.requestAirdrop(
+        kp.publicKey,
+        web3.LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(sig);
+      await program.methods
+        .registerTrader()
+        .accountsPartial({
+          trader: kp.publicKey,
+          traderAccount: account,
+          globalState: globalStateKp.publicKey,
+          systemProgram: web3.SystemProgram.programId,
+        })
+        .signers([kp])
+        .rpc();
+      const tokenAccount = await createTokenAccount(
+        provider,
+        mintKp.publicKey,
+        kp.publicKey
+      );
+      await mintTo(provider, mintKp.publicKey, tokenAccount, entry.stake);
+      await program.methods
+        .stake({ plain: [new BN(entry.stake)] })
+        .accountsPartial({
+          trader: kp.publicKey,
+          traderTokenAccount: tokenAccount,
+          traderAccount: account,
+          globalState: globalStateKp.publicKey,
+          mint: mintKp.publicKey,
+          equalityProof: null,
+          ciphertextValidityProof: null,
+          rangeProof: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([kp])
+        .rpc();
+      const { attestation, ix } = await attest(10, oracleKp, kp.publicKey);
+      await program.methods
+        .verifyPriority(
+          hex(entry.proof),
+          Array.from(hex(entry.commitment)),
+          Array.from(hex(entry.nullifier)),
+          { oracle: [attestation] },
+          VK_VERSION
+        )
+        .accountsPartial({
+          ...verifyAccounts,
+          trader: kp.publicKey,
+          traderAccount: account,
+        })
+        .preInstructions([ix])
+        .signers([kp])
+        .rpc();
+      traders.push({ kp, account, entry });
+    }
+
+    // The ask with the larger stake is revealed last.
+    for (const { kp, account, entry } of traders) {
+      await program.methods
+        .revealTrade(
+          {
+            version: entry.order.version,
+            market,
+            side: { [entry.order.side]: {} },
+            price: new BN(entry.order.price),
+            size: new BN(entry.order.size),
+            salt: Array.from(hex(entry.order.salt)),
+          },
+          hex(entry.orderRangeProof),
+          1
+        )
+        .accountsPartial({
+          trader: kp.publicKey,
+          traderAccount: account,
+          globalState: globalStateKp.publicKey,
+          verifyingKey: orderRangeKey,
+          orderBook: auctionBook,
+        })
+        .signers([kp])
+        .rpc();
+    }
+    // Asks at the same price rest in reveal order.
+    let book = await program.account.orderBook.fetch(auctionBook);
+    assert(book.batchDuration.eq(new BN(5)));
+    assert(book.asks[1].priority.gt(book.asks[0].priority));
+
+    // Clear the batch of the last reveal, which covers the earlier ones.
+    const batch = book.asks[1].batch;
+    while ((await blockTime()) < (batch.toNumber() + 1) * 5) {
+      await new Promise((resolve) => setTimeout(resolve, 1000));
+    }
+    const [auctionClearing] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("auction_clearing"), auctionBook.toBuffer(), u64(batch)],
+      program.programId
+    );
+    await program.methods
+      .clearAuction(batch)
+      .accountsPartial({
+        payer: admin,
+        globalState: globalStateKp.publicKey,
+        orderBook: auctionBook,
+        auctionClearing,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // Demand of 10 at 102 meets supply of 12 at 100: both prices clear 10,
+    // so the lower one wins. The higher-priority ask fills first.
+    const [late, bidder, asker] = traders.map(({ kp }) => kp.publicKey);
+    const clearing =
+      await program.account.auctionClearing.fetch(auctionClearing);
+    assert(clearing.clearingPrice.eq(new BN(100)));
+    assert(clearing.volume.eq(new BN(10)));
+    assert.strictEqual(clearing.fillCount, 3);
+    const fills = clearing.fills.slice(0, 3);
+    assert.deepStrictEqual(
+      fills.map((fill) => [fill.trader.toBase58(), fill.side]),
+      [
+        [bidder.toBase58(), 0],
+        [asker.toBase58(), 1],
+        [late.toBase58(), 1],
+      ]
+    );
+    assert.deepStrictEqual(
+      fills.map((fill) => fill.size.toNumber()),
+      [10, 6, 4]
+    );
+
+    // The rest of the late ask keeps resting.
+    book = await program.account.orderBook.fetch(auctionBook);
+    assert.strictEqual(book.bidCount, 0);
+    assert.strictEqual(book.askCount, 1);
+    assert(book.asks[0].trader.equals(late));
+    assert(book.asks[0].size.eq(new BN(2)));
+    assert(book.nextClearBatch.eq(batch.addn(1)));
+  });
+
+  it("withdraws treasury buckets to the admin", async () => {
+    const destination = await createTokenAccount(
+      program.provider as anchor.AnchorProvider,
+      mintKp.publicKey,
+      admin
+    );
+    const withdrawAccounts = {
+      admin,
+      globalState: globalStateKp.publicKey,
+      treasury,
+      treasuryVault,
+      destination,
+      mint: mintKp.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+
+    await assert.rejects(
+      program.methods
+        .withdrawTreasury({ insurance: {} }, new BN(50))
+        .accountsPartial(withdrawAccounts)
+        .rpc(),
+      /InsufficientTreasuryBalance/
+    );
+
+    let event: any;
+    const listener = program.addEventListener("treasuryWithdrawn", (e) => {
+      event = e;
+    });
+    await program.methods
+      .withdrawTreasury({ revenue: {} }, new BN(149))
+      .accountsPartial(withdrawAccounts)
+      .rpc();
+    await new Promise((resolve) => setTimeout(resolve, 1000));
+    await program.removeEventListener(listener);
+
+    const balance =
+      await program.provider.connection.getTokenAccountBalance(destination);
+    const state = await program.account.treasury.fetch(treasury);
+    assert.strictEqual(balance.value.amount, "149");
+    assert(state.revenueBalance.isZero());
+    assert(state.insuranceBalance.eq(new BN(49)));
+    assert(event.amount.eq(new BN(149)));
+    assert(event.destination.equals(destination));
+  });
+
+  it("rejects proofs against a retired verifying key", async () => {
+    await program.methods
+      .retireVerifyingKey()
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        verifyingKey,
+      })
+      .rpc();
+
+    await assert.rejects(
+      program.methods
+        .verifyPriority(proof, commitment, nullifier, unattested, VK_VERSION)
+        .accountsPartial(verifyAccounts)
+        .signers([traderKp])
+        .rpc(),
+      /VerifyingKeyNotActive/
+    );
+  });
+});
+
+describe("latency challenges", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.Zklr as anchor.Program<Zklr>;
+  const admin = program.provider.publicKey;
+
+  const fixture = require("./fixtures/groth16_priority.json");
+  const hex = (s: string) => Buffer.from(s, "hex");
+  const keypair = (name: string) =>
+    web3.Keypair.fromSeed(hex(fixture.keys[name]));
+  const vector = fixture.priority.proofs.latency;
+  const globalStateKp = keypair(vector.globalState);
+  const traderKp = keypair(vector.trader);
+  const trader = traderKp.publicKey;
+  const mintKp = new web3.Keypair();
+  const commitment = Array.from(hex(vector.commitment));
+  const nullifier = Array.from(hex(vector.nullifier));
+  const proof = hex(vector.proof);
+
+  const PRIORITY_CIRCUIT_ID = 0;
+  const VK_VERSION = 1;
+  const u32 = (value: number) => new BN(value).toArrayLike(Buffer, "le", 4);
+  const [verifyingKey] = web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("verifying_key"),
+      globalStateKp.publicKey.toBuffer(),
+      u32(PRIORITY_CIRCUIT_ID),
+      u32(VK_VERSION),
+    ],
+    program.programId
+  );
+  const [traderAccount] = web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("trader"),
+      globalStateKp.publicKey.toBuffer(),
+      trader.toBuffer(),
+    ],
+    program.programId
+  );
+  const [latencyChallenge] = web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("latency_challenge"),
+      globalStateKp.publicKey.toBuffer(),
+      trader.toBuffer(),
+    ],
+    program.programId
+  );
+  const challengeAccounts = {
+    trader,
+    traderAccount,
+    globalState: globalStateKp.publicKey,
+    latencyChallenge,
+    slotHashes: web3.SYSVAR_SLOT_HASHES_PUBKEY,
+    systemProgram: web3.SystemProgram.programId,
+  };
+  // The trading node whose latency is measured signs the challenge nonce.
+  const responderKp = new web3.Keypair();
+  const signNonce = (nonce: number[], signer = responderKp) =>
+    web3.Ed25519Program.createInstructionWithPrivateKey({
+      privateKey: signer.secretKey,
+      message: Buffer.concat([
+        globalStateKp.publicKey.toBuffer(),
+        trader.toBuffer(),
+        Buffer.from(nonce),
+      ]),
+    });
+  const verifyAccounts = {
+    trader,
+    traderAccount,
+    globalState: globalStateKp.publicKey,
+    verifyingKey,
+    mint: mintKp.publicKey,
+    tokenProgram: TOKEN_PROGRAM_ID,
+    latencyOracle: null,
+    latencyChallenge,
+  };
+
+  before(async () => {
+    const provider = program.provider as anchor.AnchorProvider;
+    await program.methods
+      .initialize(admin)
+      .accountsPartial({
+        globalState: globalStateKp.publicKey,
+        admin,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([globalStateKp])
+      .rpc();
+
+    const sig = await provider.connection.requestAirdrop(
+      trader,
+      web3.LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(sig);
+
+    await createMint(provider, mintKp);
+    await program.methods
+      .initializeVaults({ spl: {} })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        mint: mintKp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+
+    await program.methods
+      .initializeTreasury(2500)
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        mint: mintKp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+
+    await program.methods
+      .initializeCommitmentTree()
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // The key's points don't fit in one transaction.
+    const vk = fixture.priority.verifyingKey;
+    const ic = vk.ic.map((point: string) => Array.from(hex(point)));
+    await program.methods
+      .addVerifyingKey(PRIORITY_CIRCUIT_ID, VK_VERSION, {
+        alphaG1: Array.from(hex(vk.alphaG1)),
+        betaG2: Array.from(hex(vk.betaG2)),
+        gammaG2: Array.from(hex(vk.gammaG2)),
+        deltaG2: Array.from(hex(vk.deltaG2)),
+        ic: ic.slice(0, 5),
+      })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        verifyingKey,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .extendVerifyingKey(ic.slice(5))
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        verifyingKey,
+      })
+      .rpc();
+    await program.methods
+      .activateVerifyingKey()
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        verifyingKey,
+      })
+      .rpc();
+
+    await program.methods
+      .registerTrader()
+      .accountsPartial({
+        trader,
+        traderAccount,
+        globalState: globalStateKp.publicKey,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([traderKp])
+      .rpc();
+
+    const traderTokenAccount = await createTokenAccount(
+      provider,
+      mintKp.publicKey,
+      trader
+    );
+    await mintTo(provider, mintKp.publicKey, traderTokenAccount, 1000);
+    await program.methods
+      .stake({ plain: [new BN(1000)] })
+      .accountsPartial({
+        trader,
+        traderTokenAccount,
+        traderAccount,
+        globalState: globalStateKp.publicKey,
+        mint: mintKp.publicKey,
+        equalityProof: null,
+        ciphertextValidityProof: null,
+        rangeProof: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([traderKp])
+      .rpc();
+  });
+
+  it("only takes answered challenges as a latency source", async () => {
+    await program.methods
+      .issueLatencyChallenge(responderKp.publicKey)
+      .accountsPartial(challengeAccounts)
+      .signers([traderKp])
+      .rpc();
+
+    await assert.rejects(
+      program.methods
+        .verifyPriority(
+          proof,
+          commitment,
+          nullifier,
+          { challenge: {} },
+          VK_VERSION
+        )
+        .accountsPartial(verifyAccounts)
+        .signers([traderKp])
+        .rpc(),
+      /LatencyChallengeNotAnswered/
+    );
+  });
+
+  it("measures latency as the slots taken to sign the nonce", async () => {
+    const challenge =
+      await program.account.latencyChallenge.fetch(latencyChallenge);
+    assert(challenge.responder.equals(responderKp.publicKey));
+    await assert.rejects(
+      program.methods
+        .respondLatencyChallenge()
+        .accountsPartial(challengeAccounts)
+        .signers([traderKp])
+        .rpc(),
+      /MissingChallengeResponse/
+    );
+    // Only the responder's signature answers the challenge.
+    await assert.rejects(
+      program.methods
+        .respondLatencyChallenge()
+        .accountsPartial(challengeAccounts)
+        .preInstructions([signNonce(challenge.nonce, traderKp)])
+        .signers([traderKp])
+        .rpc(),
+      /InvalidChallengeResponse/
+    );
+    await assert.rejects(
+      program.methods
+        .respondLatencyChallenge()
+        .accountsPartial(challengeAccounts)
+        .preInstructions([signNonce(Array(32).fill(0))])
+        .signers([traderKp])
+        .rpc(),
+      /InvalidChallengeResponse/
+    );
+
+    await program.methods
+      .respondLatencyChallenge()
+      .accountsPartial(challengeAccounts)
+      .preInstructions([signNonce(challenge.nonce)])
+      .signers([traderKp])
+      .rpc();
+
+    const answered =
+      await program.account.latencyChallenge.fetch(latencyChallenge);
+    assert(answered.respondedSlot.gt(answered.postedSlot));
+
+    await assert.rejects(
+      program.methods
+        .respondLatencyChallenge()
+        .accountsPartial(challengeAccounts)
+        .preInstructions([signNonce(challenge.nonce)])
+        .signers([traderKp])
+        .rpc(),
+      /LatencyChallengeAnswered/
+    );
+  });
+
+  it("derives the speed multiplier from the challenge", async () => {
+    const challenge =
+      await program.account.latencyChallenge.fetch(latencyChallenge);
+    const latency = challenge.respondedSlot.sub(challenge.postedSlot);
+
+    await program.methods
+      .verifyPriority(
+        proof,
+        commitment,
+        nullifier,
+        { challenge: {} },
+        VK_VERSION
+      )
+      .accountsPartial(verifyAccounts)
+      .signers([traderKp])
+      .rpc();
+
+    const account = await program.account.traderAccount.fetch(traderAccount);
+    const expected = new BN(1000).div(latency.addn(1));
+    assert(account.speedMultiplier.eq(expected));
+    // The challenge is consumed by the verification.
+    assert.strictEqual(
+      await program.provider.connection.getAccountInfo(latencyChallenge),
+      null
+    );
+  });
+});
+
+describe("order book", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.Zklr as anchor.Program<Zklr>;
+  const admin = program.provider.publicKey;
+  const globalStateKp = new web3.Keypair();
+  const market = new web3.Keypair().publicKey;
+
+  const [orderBook] = web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("order_book"),
+      globalStateKp.publicKey.toBuffer(),
+      market.toBuffer(),
+    ],
+    program.programId
+  );
+
+  before(async () => {
+    await program.methods
+      .initialize(admin)
+      .accountsPartial({
+        globalState: globalStateKp.publicKey,
+        admin,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([globalStateKp])
+      .rpc();
+  });
+
+  it("initialize_order_book creates an empty book for the market", async () => {
+    const other = new web3.Keypair();
+    await assert.rejects(
+      program.methods
+        .initializeOrderBook(market)
+        .accountsPartial({
+          admin: other.publicKey,
+          globalState: globalStateKp.publicKey,
+          orderBook,
+          systemProgram: web3.SystemProgram.programId,
+        })
+        .signers([other])
+        .rpc(),
+      /Unauthorized/
+    );
+
+    await program.methods
+      .initializeOrderBook(market)
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        orderBook,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const book = await program.account.orderBook.fetch(orderBook);
+    assert(book.market.equals(market));
+    assert.strictEqual(book.bidCount, 0);
+    assert.strictEqual(book.askCount, 0);
+    assert(book.nextSequence.isZero());
+    assert(book.nextClearBatch.isZero());
+  });
+
+  it("clear_auction waits for the reveal window to end", async () => {
+    const connection = program.provider.connection;
+    const now = await connection.getBlockTime(await connection.getSlot());
+    const current = new BN(Math.floor(now / 30));
+    const clear = (batch: BN) =>
+      program.methods.clearAuction(batch).accountsPartial({
+        payer: admin,
+        globalState: globalStateKp.publicKey,
+        orderBook,
+        auctionClearing: web3.PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("auction_clearing"),
+            orderBook.toBuffer(),
+            batch.toArrayLike(Buffer, "le", 8),
+          ],
+          program.programId
+        )[0],
+        systemProgram: web3.SystemProgram.programId,
+      });
+
+    await assert.rejects(clear(current).rpc(), /AuctionWindowOpen/);
+    // The previous window has ended, but there is nothing to match.
+    await assert.rejects(clear(current.subn(1)).rpc(), /NoCrossingOrders/);
+  });
+});
+
+describe("token modes", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.Zklr as anchor.Program<Zklr>;
+  const admin = program.provider.publicKey;
+  const globalStateKp = new web3.Keypair();
+  const mintKp = new web3.Keypair();
+
+  const pda = (seed: string) =>
+    web3.PublicKey.findProgramAddressSync(
+      [Buffer.from(seed), globalStateKp.publicKey.toBuffer()],
+      program.programId
+    )[0];
+  const vaultAccounts = () => ({
+    admin,
+    globalState: globalStateKp.publicKey,
+    mint: mintKp.publicKey,
+    stakeAuthority: pda("stake_authority"),
+    stakeVault: pda("stake_vault"),
+    liquidityAuthority: pda("liquidity_authority"),
+    liquidityVault: pda("liquidity_vault"),
+    tokenProgram: TOKEN_PROGRAM_ID,
+    systemProgram: web3.SystemProgram.programId,
+    rent: web3.SYSVAR_RENT_PUBKEY,
+  });
+
+  before(async () => {
+    await program.methods
+      .initialize(admin)
+      .accountsPartial({
+        globalState: globalStateKp.publicKey,
+        admin,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([globalStateKp])
+      .rpc();
+
+    await createMint(program.provider as anchor.AnchorProvider, mintKp);
+  });
+
+  it("rejects confidential mode for a plain SPL mint", async () => {
+    await assert.rejects(
+      program.methods
+        .initializeVaults({ confidential: {} })
+        .accountsPartial(vaultAccounts())
+        .rpc(),
+      /InvalidTokenMode/
+    );
+  });
+
+  it("falls back to SPL mode for a plain SPL mint", async () => {
+    await program.methods
+      .initializeVaults({ spl: {} })
+      .accountsPartial(vaultAccounts())
+      .rpc();
+
+    const state = await program.account.globalState.fetch(
+      globalStateKp.publicKey
+    );
+    assert(state.tokenMint.equals(mintKp.publicKey));
+    assert.deepStrictEqual(state.tokenMode, { spl: {} });
+  });
+});
+
+describe("confidential mode", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.Zklr as anchor.Program<Zklr>;
+  const admin = program.provider.publicKey;
+  const globalStateKp = new web3.Keypair();
+  const mintKp = new web3.Keypair();
+  const globalState = globalStateKp.publicKey;
+  const [traderAccount] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("trader"), globalState.toBuffer(), admin.toBuffer()],
+    program.programId
+  );
+  const [lpAccount] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("lp"), globalState.toBuffer(), admin.toBuffer()],
+    program.programId
+  );
+  let tokenAccount: web3.PublicKey;
+
+  before(async () => {
+    const provider = program.provider as anchor.AnchorProvider;
+    await program.methods
+      .initialize(admin)
+      .accountsPartial({
+        globalState,
+        admin,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([globalStateKp])
+      .rpc();
+    await createConfidentialMint(provider, mintKp);
+    await program.methods
+      .initializeVaults({ confidential: {} })
+      .accountsPartial({
+        admin,
+        globalState,
+        mint: mintKp.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+    tokenAccount = await createTokenAccount(
+      provider,
+      mintKp.publicKey,
+      admin,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await program.methods
+      .registerTrader()
+      .accountsPartial({
+        trader: admin,
+        traderAccount,
+        globalState,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .registerLp()
+      .accountsPartial({
+        lp: admin,
+        lpAccount,
+        globalState,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  // Deposits are rejected too, as nothing could move them out of the vaults.
+  it("rejects stake", async () => {
+    await assert.rejects(
+      program.methods
+        .stake({ plain: [new BN(1)] })
+        .accountsPartial({
+          trader: admin,
+          traderTokenAccount: tokenAccount,
+          traderAccount,
+          globalState,
+          mint: mintKp.publicKey,
+          equalityProof: null,
+          ciphertextValidityProof: null,
+          rangeProof: null,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .rpc(),
+      /ConfidentialModeUnsupported/
+    );
+  });
+
+  it("rejects provide_liquidity", async () => {
+    await assert.rejects(
+      program.methods
+        .provideLiquidity({ plain: [new BN(1)] })
+        .accountsPartial({
+          lp: admin,
+          lpTokenAccount: tokenAccount,
+          lpAccount,
+          globalState,
+          mint: mintKp.publicKey,
+          equalityProof: null,
+          ciphertextValidityProof: null,
+          rangeProof: null,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .rpc(),
+      /ConfidentialModeUnsupported/
+    );
+  });
+
+  it("rejects unstake", async () => {
+    await assert.rejects(
+      program.methods
+        .unstake(new BN(1))
+        .accountsPartial({
+          trader: admin,
+          traderAccount,
+          traderTokenAccount: tokenAccount,
+          globalState,
+          mint: mintKp.publicKey,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .rpc(),
+      /ConfidentialModeUnsupported/
+    );
+  });
+
+  it("rejects withdraw_liquidity", async () => {
+    await assert.rejects(
+      program.methods
+        .withdrawLiquidity(new BN(1))
+        .accountsPartial({
+          lp: admin,
+          lpAccount,
+          lpTokenAccount: tokenAccount,
+          globalState,
+          mint: mintKp.publicKey,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .rpc(),
+      /ConfidentialModeUnsupported/
+    );
+  });
+
+  it("rejects allocate_bandwidth", async () => {
+    const connection = program.provider.connection;
+    const now = await connection.getBlockTime(await connection.getSlot());
+    const epoch = new BN(Math.floor(now / 300) + 1);
+    const seeds = (seed: string, ...rest: Buffer[]) =>
+      web3.PublicKey.findProgramAddressSync(
+        [
+          Buffer.from(seed),
+          globalState.toBuffer(),
+          ...rest,
+          epoch.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      )[0];
+    const bandwidthEpoch = seeds("bandwidth_epoch");
+    await program.methods
+      .openBandwidthEpoch(epoch)
+      .accountsPartial({
+        payer: admin,
+        globalState,
+        bandwidthEpoch,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await assert.rejects(
+      program.methods
+        .allocateBandwidth(epoch)
+        .accountsPartial({
+          trader: admin,
+          traderAccount,
+          globalState,
+          bandwidthEpoch,
+          bandwidthTicket: seeds("bandwidth_ticket", admin.toBuffer()),
+          systemProgram: web3.SystemProgram.programId,
+        })
+        .rpc(),
+      /ConfidentialModeUnsupported/
+    );
+  });
+});
+
+describe("protocol config", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.Zklr as anchor.Program<Zklr>;
+  const admin = program.provider.publicKey;
+  const globalStateKp = new web3.Keypair();
+  const [protocolConfig] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("protocol_config"), globalStateKp.publicKey.toBuffer()],
+    program.programId
+  );
+
+  before(async () => {
+    await program.methods
+      .initialize(admin)
+      .accountsPartial({
+        globalState: globalStateKp.publicKey,
+        protocolConfig,
+        admin,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([globalStateKp])
+      .rpc();
+  });
+
+  it("starts with the default parameters", async () => {
+    const config = await program.account.protocolConfig.fetch(protocolConfig);
+    assert(config.proofValidityPeriod.eq(new BN(3600)));
+    assert.strictEqual(config.feePercentage, 1);
+    assert.strictEqual(config.maxInvalidProofs, 3);
+    assert(config.liquidityLockPeriod.eq(new BN(86400)));
+    assert(config.auctionBatchDuration.eq(new BN(30)));
+  });
+
+  it("update_config checks bounds and emits the change", async () => {
+    const config = await program.account.protocolConfig.fetch(protocolConfig);
+    const { bump, ...params } = config;
+
+    await assert.rejects(
+      program.methods
+        .updateConfig({ ...params, feePercentage: 50 })
+        .accountsPartial({
+          admin,
+          globalState: globalStateKp.publicKey,
+          protocolConfig,
+        })
+        .rpc(),
+      /InvalidConfigValue/
+    );
+
+    let event: any;
+    const listener = program.addEventListener("configUpdated", (e) => {
+      event = e;
+    });
+    await program.methods
+      .updateConfig({ ...params, revealDelay: new BN(60) })
+      .accountsPartial({
+        admin,
+        globalState: globalStateKp.publicKey,
+        protocolConfig,
+      })
+      .rpc();
+    await new Promise((resolve) => setTimeout(resolve, 1000));
+    await program.removeEventListener(listener);
+
+    const updated = await program.account.protocolConfig.fetch(protocolConfig);
+    assert(updated.revealDelay.eq(new BN(60)));
+    assert(event.old.revealDelay.eq(new BN(30)));
+    assert(event.new.revealDelay.eq(new BN(60)));
+  });
+
+  it("update_config is admin-only", async () => {
+    const outsider = new web3.Keypair();
+    const { bump, ...params } =
+      await program.account.protocolConfig.fetch(protocolConfig);
+    await assert.rejects(
+      program.methods
+        .updateConfig(params)
+        .accountsPartial({
+          admin: outsider.publicKey,
+          globalState: globalStateKp.publicKey,
+          protocolConfig,
+        })
+        .signers([outsider])
+        .rpc(),
+      /Unauthorized/
+    );
+  });
+});
+
+describe("admin handover", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.Zklr as anchor.Program<Zklr>;
+  const admin = program.provider.publicKey;
+  const globalStateKp = new web3.Keypair();
+  const newAdminKp = new web3.Keypair();
+
+  before(async () => {
+    await program.methods
+      .initialize(admin)
+      .accountsPartial({
+        globalState: globalStateKp.publicKey,
+        admin,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([globalStateKp])
+      .rpc();
+  });
+
+  it("moves admin control once the proposed admin accepts", async () => {
+    await program.methods
+      .proposeAdmin(newAdminKp.publicKey)
+      .accountsPartial({ admin, globalState: globalStateKp.publicKey })
+      .rpc();
+
+    const outsider = new web3.Keypair();
+    await assert.rejects(
+      program.methods
+        .acceptAdmin()
+        .accountsPartial({
+          newAdmin: outsider.publicKey,
+          globalState: globalStateKp.publicKey,
+        })
+        .signers([outsider])
+        .rpc(),
+      /Unauthorized/
+    );
+
+    await program.methods
+      .acceptAdmin()
+      .accountsPartial({
+        newAdmin: newAdminKp.publicKey,
+        globalState: globalStateKp.publicKey,
+      })
+      .signers([newAdminKp])
+      .rpc();
+
+    const state = await program.account.globalState.fetch(
+      globalStateKp.publicKey
+    );
+    assert(state.admin.equals(newAdminKp.publicKey));
+    assert.strictEqual(state.pendingAdmin, null);
+  });
+
+  it("renounce_admin freezes admin control", async () => {
+    await program.methods
+      .renounceAdmin()
+      .accountsPartial({
+        admin: newAdminKp.publicKey,
+        globalState: globalStateKp.publicKey,
+      })
+      .signers([newAdminKp])
+      .rpc();
+
+    await assert.rejects(
+      program.methods
+        .proposeAdmin(admin)
+        .accountsPartial({
+          admin: newAdminKp.publicKey,
+          globalState: globalStateKp.publicKey,
+        })
+        .signers([newAdminKp])
+        .rpc(),
+      /Unauthorized/
+    );
+  });
+});
+
+describe("pause", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.Zklr as anchor.Program<Zklr>;
+  const admin = program.provider.publicKey;
+  const globalStateKp = new web3.Keypair();
+  const PAUSE_LIQUIDITY = 1 << 4;
+
+  const registerLp = () =>
+    program.methods
+      .registerLp()
+      .accountsPartial({
+        lp: admin,
+        globalState: globalStateKp.publicKey,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+  before(async () => {
+    await program.methods
+      .initialize(admin)
+      .accountsPartial({
+        globalState: globalStateKp.publicKey,
+        admin,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([globalStateKp])
+      .rpc();
+  });
+
+  it("rejects unknown pause flags", async () => {
+    await assert.rejects(
+      program.methods
+        .setPaused(1 << 7)
+        .accountsPartial({ admin, globalState: globalStateKp.publicKey })
+        .rpc(),
+      /InvalidConfigValue/
+    );
+  });
+
+  it("blocks a paused instruction class until it is unpaused", async () => {
+    await program.methods
+      .setPaused(PAUSE_LIQUIDITY)
+      .accountsPartial({ admin, globalState: globalStateKp.publicKey })
+      .rpc();
+    await assert.rejects(registerLp(), /ProtocolPaused/);
+
+    await program.methods
+      .setPaused(0)
+      .accountsPartial({ admin, globalState: globalStateKp.publicKey })
+      .rpc();
+    await registerLp();
+  });
+});
@@ -0,0 +1,371 @@
+//! Generates `tests/fixtures/groth16_priority.json`, with the priority, order range and pool
+//! membership keys, and the orders of a batch auction.
+//!
+//! The circuits are toys: each one proves knowledge of `w` such that
+//! `w * (sum of public inputs) = 1`. That is enough to exercise the on-chain
+//! verifier and the way the program lays out public inputs, but proves nothing
+//! about the trader. Keypairs come from fixed seeds so the tests can rebuild them
+//! with `Keypair.fromSeed`.
+//!
+//! Usage: `cargo run --release > ../groth16_priority.json`
+
+use ark_bn254::{Bn254, Fq, Fr, G1Affine, G2Affine};
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_groth16::{Groth16, Proof, ProvingKey, VerifyingKey};
+use ark_relations::lc;
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystemRef, LinearCombination, SynthesisError, Variable,
+};
+use ark_snark::SNARK;
+use ark_std::rand::{rngs::StdRng, SeedableRng};
+use ed25519_dalek::SigningKey;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+/// Number of public inputs of the priority circuit: commitment, nullifier, trader and global
+/// state, each split into high and low halves.
+const PRIORITY_PUBLIC_INPUTS: usize = 8;
+
+/// Number of public inputs of the order range circuit: the commitment, split into high and low
+/// halves.
+const ORDER_RANGE_PUBLIC_INPUTS: usize = 2;
+
+/// Number of public inputs of the pool membership circuit: membership root and nullifier, each
+/// split into high and low halves, and the epoch.
+const POOL_MEMBERSHIP_PUBLIC_INPUTS: usize = 5;
+
+/// Proves knowledge of `w` such that `w * (sum of public inputs) = 1`.
+struct ToyCircuit {
+    inputs: Vec<Option<Fr>>,
+}
+
+impl ToyCircuit {
+    fn blank(public_inputs: usize) -> Self {
+        Self {
+            inputs: vec![None; public_inputs],
+        }
+    }
+
+    fn new(inputs: &[Fr]) -> Self {
+        Self {
+            inputs: inputs.iter().copied().map(Some).collect(),
+        }
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for ToyCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let mut sum = lc!();
+        for input in &self.inputs {
+            let variable =
+                cs.new_input_variable(|| input.ok_or(SynthesisError::AssignmentMissing))?;
+            sum = sum + variable;
+        }
+        let w = cs.new_witness_variable(|| {
+            let mut total = Fr::from(0u64);
+            for input in &self.inputs {
+                total += input.ok_or(SynthesisError::AssignmentMissing)?;
+            }
+            total.inverse().ok_or(SynthesisError::Unsatisfiable)
+        })?;
+        cs.enforce_constraint(
+            LinearCombination::from(w),
+            sum,
+            LinearCombination::from(Variable::One),
+        )
+    }
+}
+
+/// A circuit's keys, generated from a fixed seed.
+struct Circuit {
+    pk: ProvingKey<Bn254>,
+    vk: VerifyingKey<Bn254>,
+    rng: StdRng,
+}
+
+impl Circuit {
+    fn setup(public_inputs: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (pk, vk) =
+            Groth16::<Bn254>::circuit_specific_setup(ToyCircuit::blank(public_inputs), &mut rng)
+                .expect("setup");
+        Self { pk, vk, rng }
+    }
+
+    fn prove(&mut self, inputs: &[Fr]) -> Proof<Bn254> {
+        let proof = Groth16::<Bn254>::prove(&self.pk, ToyCircuit::new(inputs), &mut self.rng)
+            .expect("prove");
+        assert!(Groth16::<Bn254>::verify(&self.vk, inputs, &proof).expect("verify"));
+        proof
+    }
+}
+
+/// Order as `reveal_trade` decodes it. Traders commit to `sha256` of its Borsh encoding.
+struct Order {
+    version: u8,
+    market: [u8; 32],
+    /// 0 for a bid, 1 for an ask, as the `OrderSide` variant index.
+    side: u8,
+    price: u64,
+    size: u64,
+    salt: [u8; 32],
+}
+
+impl Order {
+    /// Borsh encoding: fields in declaration order, integers little-endian.
+    fn encode(&self) -> Vec<u8> {
+        [
+            &[self.version][..],
+            &self.market,
+            &[self.side],
+            &self.price.to_le_bytes(),
+            &self.size.to_le_bytes(),
+            &self.salt,
+        ]
+        .concat()
+    }
+
+    fn commitment(&self) -> [u8; 32] {
+        Sha256::digest(self.encode()).into()
+    }
+
+    fn json(&self) -> Value {
+        json!({
+            "version": self.version,
+            "market": hex(&self.market),
+            "side": if self.side == 0 { "bid" } else { "ask" },
+            "price": self.price,
+            "size": self.size,
+            "salt": hex(&self.salt),
+            "encoded": hex(&self.encode()),
+        })
+    }
+}
+
+/// Deterministic test keypair: the seed and its ed25519 public key.
+struct Key {
+    seed: [u8; 32],
+    pubkey: [u8; 32],
+}
+
+impl Key {
+    fn new(name: &str) -> Self {
+        let seed: [u8; 32] = Sha256::digest(format!("zklr-test-key:{name}")).into();
+        let pubkey = SigningKey::from_bytes(&seed).verifying_key().to_bytes();
+        Self { seed, pubkey }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn fq(x: &Fq) -> Vec<u8> {
+    x.into_bigint().to_bytes_be()
+}
+
+fn g1(p: &G1Affine) -> Vec<u8> {
+    [fq(&p.x), fq(&p.y)].concat()
+}
+
+fn g2(p: &G2Affine) -> Vec<u8> {
+    [fq(&p.x.c1), fq(&p.x.c0), fq(&p.y.c1), fq(&p.y.c0)].concat()
+}
+
+fn verifying_key_json(vk: &VerifyingKey<Bn254>) -> Value {
+    json!({
+        "alphaG1": hex(&g1(&vk.alpha_g1)),
+        "betaG2": hex(&g2(&vk.beta_g2)),
+        "gammaG2": hex(&g2(&vk.gamma_g2)),
+        "deltaG2": hex(&g2(&vk.delta_g2)),
+        "ic": vk.gamma_abc_g1.iter().map(|p| hex(&g1(p))).collect::<Vec<_>>(),
+    })
+}
+
+fn proof_hex(proof: &Proof<Bn254>) -> String {
+    hex(&[g1(&proof.a), g2(&proof.b), g1(&proof.c)].concat())
+}
+
+/// Splits 32 bytes into two 128-bit field elements (high half, low half), as the program does.
+fn split(bytes: &[u8; 32]) -> [Fr; 2] {
+    [
+        Fr::from_be_bytes_mod_order(&bytes[..16]),
+        Fr::from_be_bytes_mod_order(&bytes[16..]),
+    ]
+}
+
+/// Public inputs of the priority circuit, in the order `groth16::priority_public_inputs` uses.
+fn priority_inputs(
+    commitment: &[u8; 32],
+    nullifier: &[u8; 32],
+    trader: &[u8; 32],
+    global_state: &[u8; 32],
+) -> Vec<Fr> {
+    let inputs = [
+        split(commitment),
+        split(nullifier),
+        split(trader),
+        split(global_state),
+    ]
+    .concat();
+    assert_eq!(inputs.len(), PRIORITY_PUBLIC_INPUTS);
+    inputs
+}
+
+/// Nullifier of a trader's commitment. A real circuit derives it from the trader's secret and key.
+fn nullifier(trader_key: &[u8; 32], order: &Order) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(b"zklr-test-nullifier:")
+        .chain_update(trader_key)
+        .chain_update(order.encode())
+        .finalize()
+        .into()
+}
+
+fn main() {
+    let key_names = [
+        "groth16GlobalState",
+        "groth16Trader",
+        "latencyGlobalState",
+        "latencyTrader",
+        "auctionBidder",
+        "auctionAsker",
+        "auctionLateAsker",
+    ];
+    let keys: Map<String, Value> = key_names
+        .iter()
+        .map(|name| (name.to_string(), json!(hex(&Key::new(name).seed))))
+        .collect();
+
+    let order = Order {
+        version: 1,
+        market: Sha256::digest("zklr-test-market").into(),
+        side: 0,
+        price: 100,
+        size: 10,
+        salt: Sha256::digest("zklr-test-order-salt").into(),
+    };
+    let commitment = order.commitment();
+    let mut priority = Circuit::setup(PRIORITY_PUBLIC_INPUTS, 42);
+    let mut proofs = Map::new();
+    for (name, global_state, trader) in [
+        ("groth16", "groth16GlobalState", "groth16Trader"),
+        ("latency", "latencyGlobalState", "latencyTrader"),
+    ] {
+        let trader_key = Key::new(trader).pubkey;
+        let nullifier = nullifier(&trader_key, &order);
+        let inputs = priority_inputs(
+            &commitment,
+            &nullifier,
+            &trader_key,
+            &Key::new(global_state).pubkey,
+        );
+        let proof = priority.prove(&inputs);
+        proofs.insert(
+            name.to_string(),
+            json!({
+                "globalState": global_state,
+                "trader": trader,
+                "commitment": hex(&commitment),
+                "nullifier": hex(&nullifier),
+                "proof": proof_hex(&proof),
+            }),
+        );
+    }
+
+    // The epoch a slot is claimed for depends on when the tests run, so the toy membership
+    // circuit leaves it out: it only has the root and nullifier inputs, and the epoch's IC point
+    // is the point at infinity, which makes the proof valid for any epoch.
+    let member_secret = "pool member secret";
+    let member_commitment: [u8; 32] = Sha256::digest(member_secret).into();
+    let membership_root: [u8; 32] = Sha256::new()
+        .chain_update([0u8; 32])
+        .chain_update(member_commitment)
+        .finalize()
+        .into();
+    let pool_nullifier: [u8; 32] = Sha256::digest("zklr-test-pool-nullifier").into();
+    let mut membership = Circuit::setup(POOL_MEMBERSHIP_PUBLIC_INPUTS - 1, 43);
+    let membership_proof =
+        membership.prove(&[split(&membership_root), split(&pool_nullifier)].concat());
+    let mut any_epoch_vk = membership.vk.clone();
+    any_epoch_vk.gamma_abc_g1.push(G1Affine::identity());
+    let mut any_epoch_inputs = [split(&membership_root), split(&pool_nullifier)].concat();
+    any_epoch_inputs.push(Fr::from(u64::MAX));
+    assert!(
+        Groth16::<Bn254>::verify(&any_epoch_vk, &any_epoch_inputs, &membership_proof)
+            .expect("verify")
+    );
+    let mut membership_vk = verifying_key_json(&membership.vk);
+    membership_vk["ic"]
+        .as_array_mut()
+        .expect("ic")
+        .push(json!(hex(&[0u8; 64])));
+
+    let mut order_range = Circuit::setup(ORDER_RANGE_PUBLIC_INPUTS, 44);
+    let order_range_proof = order_range.prove(&split(&commitment));
+
+    // Crossing orders of one batch auction, in reveal order. Both asks are at the same price, so
+    // the ask staking more fills first even though it is revealed last.
+    let auction_market: [u8; 32] = Sha256::digest("zklr-test-auction-market").into();
+    let mut auction_orders = Vec::new();
+    for (trader, stake, side, price, size) in [
+        ("auctionLateAsker", 1000u64, 1u8, 100u64, 6u64),
+        ("auctionBidder", 1000, 0, 102, 10),
+        ("auctionAsker", 2000, 1, 100, 6),
+    ] {
+        let order = Order {
+            version: 1,
+            market: auction_market,
+            side,
+            price,
+            size,
+            salt: Sha256::digest(format!("zklr-test-order-salt:{trader}")).into(),
+        };
+        let commitment = order.commitment();
+        let trader_key = Key::new(trader).pubkey;
+        let nullifier = nullifier(&trader_key, &order);
+        let inputs = priority_inputs(
+            &commitment,
+            &nullifier,
+            &trader_key,
+            &Key::new("groth16GlobalState").pubkey,
+        );
+        auction_orders.push(json!({
+            "trader": trader,
+            "stake": stake,
+            "order": order.json(),
+            "commitment": hex(&commitment),
+            "nullifier": hex(&nullifier),
+            "proof": proof_hex(&priority.prove(&inputs)),
+            "orderRangeProof": proof_hex(&order_range.prove(&split(&commitment))),
+        }));
+    }
+
+    let fixture = json!({
+        "circuit": "knowledge of w such that w * (sum of public inputs) = 1",
+        "keys": keys,
+        "priority": {
+            "verifyingKey": verifying_key_json(&priority.vk),
+            "proofs": proofs,
+        },
+        "order": order.json(),
+        "orderRange": {
+            "verifyingKey": verifying_key_json(&order_range.vk),
+            "commitment": hex(&commitment),
+            "proof": proof_hex(&order_range_proof),
+        },
+        "poolMembership": {
+            "verifyingKey": membership_vk,
+            "memberSecret": member_secret,
+            "memberCommitment": hex(&member_commitment),
+            "membershipRoot": hex(&membership_root),
+            "nullifier": hex(&pool_nullifier),
+            "proof": proof_hex(&membership_proof),
+        },
+        "auction": {
+            "market": hex(&auction_market),
+            "orders": auction_orders,
+        },
+    });
+    println!("{}", serde_json::to_string_pretty(&fixture).expect("json"));
+}